@@ -1,14 +1,27 @@
 use crate::ast_iterators::print_ast;
 use crate::cli::*;
+use crate::compile_commands::*;
+use crate::diagnostics::*;
 use crate::error::CLIError::*;
 use crate::error::*;
+use crate::fixture_generation::*;
 use crate::fs_iterators::*;
+use crate::implementation_generation::*;
+use crate::interface_extraction::*;
 use crate::mock_generation::*;
+use crate::patch::*;
+use crate::printer_generation::*;
+use crate::spy_generation::*;
+use crate::suggestions::*;
+use crate::system_includes::*;
+use clang::diagnostic::Severity;
 use clang::*;
+use colored::Colorize;
 use std::path::{Path, PathBuf};
 
 pub struct MockeryApp<'i> {
     tu: TranslationUnit<'i>,
+    ignore_parse_errors: bool,
 }
 
 impl<'i> MockeryApp<'i> {
@@ -16,80 +29,548 @@ impl<'i> MockeryApp<'i> {
         let source_file = std::fs::canonicalize(match &opts.subcmd {
             SubCommand::Create(crt) => &crt.interface_source[..],
             SubCommand::Update(upd) => &upd.mock_source[..],
+            SubCommand::Implement(imp) => &imp.interface_source[..],
+            SubCommand::Fixture(fix) => &fix.source[..],
+            SubCommand::Expect(exp) => &exp.interface_source[..],
+            SubCommand::ExtractInterface(ext) => &ext.source[..],
             SubCommand::Dump(dmp) => &dmp.source[..],
         })
         .map_err(|e| SourceFileNotFound(e))?;
 
-        let compile_db_dir = opts
+        let compile_db_dir = match opts
             .compile_commands
             .as_ref()
             .map(|s| Ok(PathBuf::from(s)))
             .unwrap_or_else(|| {
-                find_compilation_database(source_file.parent().unwrap(), opts.search_radius)
-            })
-            .and_then(|dir| {
+                find_compilation_database(&source_file, opts.search_radius, opts.verbose > 0)
+            }) {
+            Err(CompilationDatabaseSearchFailed)
+                if opts.std.is_some() || !opts.include_directories.is_empty() =>
+            {
+                None
+            }
+            result => Some(result.and_then(|dir| {
                 std::fs::canonicalize(dir).map_err(|e| SpecifiedCompilationDatabaseNotFound(e))
-            })?;
-
-        compile_db_dir
-            .join("compile_commands.json")
-            .canonicalize()
-            .map_err(|e| SpecifiedCompilationDatabaseNotFound(e))?;
-
-        std::env::set_current_dir(&compile_db_dir).unwrap();
-
-        let compile_db = CompilationDatabase::from_directory(&compile_db_dir).unwrap();
-
-        let commands = compile_db
-            .get_compile_commands(&source_file)
-            .map_err(|()| CompileCommandNotFound(source_file.clone()))?;
-        let command_vec = commands.get_commands();
-        let command = command_vec
-            .first()
-            .ok_or(CompileCommandNotFound(source_file))?;
-        let filename = command.get_filename();
-        let args: Vec<_> = command
-            .get_arguments()
-            .into_iter()
-            .filter(|a| PathBuf::from(a) != filename)
-            .filter(|a| !["/Tc", "/TC", "/Tp", "/TP"].contains(&a.as_str()))
-            .collect();
+            })?),
+        };
+
+        let candidates = match compile_db_dir {
+            Some(compile_db_dir) if compile_db_dir.join(COMPILE_COMMANDS_JSON).exists() => {
+                let compile_db = CompilationDatabase::from_directory(&compile_db_dir).unwrap();
+
+                let commands: Vec<_> = compile_db
+                    .get_compile_commands(&source_file)
+                    .map(|commands| {
+                        commands
+                            .get_commands()
+                            .iter()
+                            .map(get_compile_arguments)
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                if commands.is_empty() && is_header(&source_file) {
+                    infer_header_arguments(index, &compile_db, &source_file, |args| {
+                        adjust_arguments(
+                            args,
+                            &opts.extra_arg_before,
+                            &opts.extra_arg,
+                            &opts.remove_arg,
+                        )
+                    })
+                    .into_iter()
+                    .collect()
+                } else {
+                    commands
+                }
+            }
+            Some(compile_db_dir) => {
+                let flags = std::fs::read_to_string(compile_db_dir.join(COMPILE_FLAGS_TXT))
+                    .map_err(SpecifiedCompilationDatabaseNotFound)?;
+
+                vec![get_fixed_arguments(
+                    &source_file,
+                    resolve_relative_paths(parse_compile_flags(&flags), &compile_db_dir, false),
+                )]
+            }
+            None => vec![get_fixed_arguments(
+                &source_file,
+                opts.std
+                    .iter()
+                    .map(|std| format!("-std={}", std))
+                    .chain(
+                        opts.include_directories
+                            .iter()
+                            .map(|dir| format!("-I{}", dir)),
+                    )
+                    .collect(),
+            )],
+        };
+        if candidates.is_empty() {
+            return Err(CompileCommandNotFound(source_file));
+        }
 
-        // Parse a source file into a translation unit
-        let tu = index
-            .parser(filename)
-            .arguments(&args)
-            .parse()
-            .map_err(|e| SourceError(e))?;
+        let command_count = candidates.len();
+        let candidates: Vec<_> = select_compile_arguments(
+            candidates,
+            opts.command_index,
+            opts.command_match.as_deref(),
+        )
+        .into_iter()
+        .map(|(i, compile_args)| {
+            let mut compile_args = adjust_arguments(
+                compile_args,
+                &opts.extra_arg_before,
+                &opts.extra_arg,
+                &opts.remove_arg,
+            );
+            if !opts.no_system_include_detection && !compile_args.cl_driver {
+                if let Some(includes) = compile_args.compiler.as_ref().and_then(|compiler| {
+                    detect_system_includes(
+                        compiler,
+                        compile_args.get_language(),
+                        &compile_args.arguments,
+                        &opts.query_driver,
+                    )
+                }) {
+                    compile_args.arguments.extend(includes.get_arguments());
+                }
+            }
+            (i, compile_args)
+        })
+        .collect();
+        if candidates.is_empty() {
+            return Err(NoMatchingCompileCommand(
+                source_file,
+                opts.command_index,
+                opts.command_match.clone(),
+            ));
+        }
+
+        // Parse a source file into a translation unit, preferring the first compile command which
+        // parses without errors if there are several candidates
+        let mut chosen = None;
+        for (i, compile_args) in &candidates {
+            let tu = index
+                .parser(&compile_args.filename)
+                .arguments(&compile_args.get_parser_arguments())
+                .parse()
+                .map_err(SourceError)?;
+            let has_errors = tu
+                .get_diagnostics()
+                .iter()
+                .any(|diagnostic| diagnostic.get_severity() >= Severity::Error);
+            if !has_errors || chosen.is_none() {
+                chosen = Some((*i, compile_args, tu));
+            }
+            if !has_errors {
+                break;
+            }
+        }
+        let (i, compile_args, tu) = chosen.unwrap();
+
+        if opts.verbose > 0 {
+            eprintln!(
+                "Using compile command {} (of {}) for '{}':\n\t{}",
+                i,
+                command_count,
+                compile_args.filename.to_str().unwrap(),
+                compile_args.get_parser_arguments().join(" ")
+            );
+        }
 
-        Ok(MockeryApp { tu })
+        for diagnostic in tu.get_diagnostics() {
+            if diagnostic.get_severity() >= Severity::Warning {
+                eprintln!("{}", format_diagnostic(&diagnostic));
+            }
+        }
+
+        Ok(MockeryApp {
+            tu,
+            ignore_parse_errors: opts.ignore_parse_errors,
+        })
     }
 
     pub fn run_create(&self, crt: CreateOpts) -> CLIResult<()> {
-        let interface_name = crt.interface.as_ref().map_or(
-            Path::new(&crt.interface_source)
-                .file_stem()
-                .and_then(std::ffi::OsStr::to_str)
-                .unwrap(),
-            String::as_str,
-        );
-        if let Some(class) = find_class_entity(&self.tu, interface_name) {
-            let mock_class_name = &crt
-                .mock
-                .unwrap_or(format!("{}Mock", class.get_display_name().unwrap()));
-            let mock_def = generate_mock_definition(class, &mock_class_name);
-
-            println!("{}", mock_def);
-            Ok(())
+        let classes = if crt.all {
+            let classes = find_abstract_classes(&self.tu);
+            for &class in &classes {
+                self.check_parse_errors(class)?;
+            }
+            classes
+        } else if crt.interface.is_empty() {
+            vec![self.find_interface(get_file_stem(&crt.interface_source), true)?]
         } else {
-            Err(InterfaceClassNotFound(interface_name.to_string()))
+            crt.interface
+                .iter()
+                .map(|interface_name| self.find_interface(interface_name, false))
+                .collect::<CLIResult<_>>()?
+        };
+        let mut mockable_classes = Vec::new();
+        for class in classes {
+            if let Some(reason) = get_unmockable_reason(class) {
+                let interface_name = get_qualified_name(class);
+                if reason.is_fatal() || !crt.allow_empty {
+                    // A single unmockable class should not abort the generation of all mocks.
+                    if crt.all {
+                        eprintln!(
+                            "{} skipping `{}` because {}",
+                            "warning:".yellow().bold(),
+                            interface_name.yellow(),
+                            reason
+                        );
+                        continue;
+                    }
+                    return Err(UnmockableInterface(interface_name, reason));
+                }
+                eprintln!(
+                    "{} the mock of `{}` is empty because {}",
+                    "warning:".yellow().bold(),
+                    interface_name.yellow(),
+                    reason
+                );
+            }
+            mockable_classes.push(class);
+        }
+        let classes = mockable_classes;
+        if crt.mock.len() > classes.len() {
+            return Err(ArgumentCountMismatch(
+                "--mock",
+                crt.mock.len(),
+                classes.len(),
+            ));
+        }
+        if crt.output.len() > 1 && crt.output.len() != classes.len() {
+            return Err(ArgumentCountMismatch(
+                "--output",
+                crt.output.len(),
+                classes.len(),
+            ));
+        }
+
+        let suffix = match crt.backend {
+            Backend::Gmock => "Mock",
+            Backend::Spy => "Spy",
+        };
+        let preamble = match crt.backend {
+            Backend::Gmock => "",
+            Backend::Spy => {
+                "#include <functional>\n#include <tuple>\n#include <utility>\n#include <vector>\n\n"
+            }
+        };
+        let options = MockOptions {
+            default_returns: crt.default_return.clone(),
+            delegate_to: crt.delegate_to.clone(),
+            mock_callbacks: crt.mock_callbacks,
+            nice_alias: crt.nice,
+            strict_alias: crt.strict,
+        };
+
+        let mocks: Vec<_> = classes
+            .iter()
+            .copied()
+            .enumerate()
+            .map(|(i, class)| {
+                let mock_class_name = crt.mock.get(i).cloned().unwrap_or(format!(
+                    "{}{}",
+                    class.get_display_name().unwrap(),
+                    suffix
+                ));
+                let mock_def = match crt.backend {
+                    Backend::Gmock => generate_mock_definition(class, &mock_class_name, &options),
+                    Backend::Spy => generate_spy_definition(class, &mock_class_name),
+                };
+                (class, mock_class_name, mock_def)
+            })
+            .collect();
+
+        // Mocks of same-named classes from different namespaces would overwrite each other's
+        // header or redefine each other.
+        for (_, mock_class_name, _) in &mocks {
+            let colliding: Vec<_> = mocks
+                .iter()
+                .filter(|(_, other_name, _)| other_name == mock_class_name)
+                .map(|(other, _, _)| get_qualified_name(*other))
+                .collect();
+            if colliding.len() > 1 {
+                return Err(MockNameCollision(mock_class_name.clone(), colliding));
+            }
         }
+
+        // Helpers for types shared by several interfaces are emitted once in the combined output,
+        // and are guarded against redefinition when the mocks are written to separate headers.
+        let format_helpers = |classes: &[Entity]| {
+            if crt.print_to || crt.equality {
+                generate_parameter_type_helpers(classes, crt.print_to, crt.equality)
+                    .into_iter()
+                    .map(|helper| format!("{}\n\n", helper))
+                    .collect()
+            } else {
+                String::new()
+            }
+        };
+
+        if let Some(dir) = &crt.output_dir {
+            for (class, mock_class_name, mock_def) in &mocks {
+                let path = Path::new(dir).join(format!("{}.h", mock_class_name));
+                write_output(
+                    path.to_str(),
+                    &format!(
+                        "#pragma once\n\n{}{}{}\n",
+                        preamble,
+                        format_helpers(&[*class]),
+                        mock_def
+                    ),
+                )?;
+            }
+        } else if crt.output.len() > 1 {
+            for ((class, _, mock_def), output) in mocks.iter().zip(&crt.output) {
+                write_output(
+                    Some(output),
+                    &format!("{}{}{}\n", preamble, format_helpers(&[*class]), mock_def),
+                )?;
+            }
+        }
+
+        let combined = format!(
+            "{}{}{}",
+            preamble,
+            format_helpers(&classes),
+            mocks
+                .iter()
+                .map(|(_, _, mock_def)| mock_def.as_str())
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        );
+        if let [output] = &crt.output[..] {
+            write_output(Some(output), &format!("{}\n", combined))?;
+        }
+        if crt.stdout || (crt.output.is_empty() && crt.output_dir.is_none()) {
+            write_output(None, &combined)?;
+        }
+
+        Ok(())
     }
 
     pub fn run_update(&self, _upd: UpdateOpts) -> CLIResult<()> {
         Err(NotYetImplemented)
     }
 
+    pub fn run_implement(&self, imp: ImplementOpts) -> CLIResult<()> {
+        let interface_name = imp
+            .interface
+            .as_ref()
+            .map_or(get_file_stem(&imp.interface_source), String::as_str);
+        let class = self.find_interface(interface_name, imp.interface.is_none())?;
+
+        let interface_header = class
+            .get_location()
+            .and_then(|loc| loc.get_file_location().file)
+            .map(|file| file.get_path());
+        let header_name = imp
+            .header
+            .as_ref()
+            .map_or(format!("{}.h", imp.class), |header| {
+                get_file_name(header).to_string()
+            });
+
+        let header = format!(
+            "#pragma once\n\n{}{}\n",
+            interface_header.map_or(String::new(), |path| format!(
+                "#include \"{}\"\n\n",
+                get_file_name(path.to_str().unwrap())
+            )),
+            generate_implementation_declaration(class, &imp.class)
+        );
+        let source = format!(
+            "#include \"{}\"\n\n#include <exception>\n#include <stdexcept>\n\n{}\n",
+            header_name,
+            generate_implementation_definitions(class, &imp.class)
+        );
+
+        write_output(imp.header.as_deref(), &header)?;
+        write_output(imp.source.as_deref(), &source)
+    }
+
+    pub fn run_fixture(&self, fix: FixtureOpts) -> CLIResult<()> {
+        let class_name = fix
+            .class
+            .as_ref()
+            .map_or(get_file_stem(&fix.source), String::as_str);
+        let class = self.find_class(class_name, ClassUnderTestNotFound)?;
+        let constructor = find_injecting_constructor(class);
+        if constructor.is_none() && !has_implicit_default_constructor(class) {
+            return Err(ConstructorNotFound(class_name.to_string()));
+        }
+
+        let mut mock_definitions = Vec::new();
+        for dependency in constructor
+            .and_then(|ctor| ctor.get_arguments())
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(get_dependency)
+        {
+            let interface_name = dependency.interface.get_name().unwrap();
+            let mock_name = format!("{}Mock", interface_name);
+            if find_class_entity(&self.tu, &mock_name).is_none() {
+                let mock_def = generate_mock_definition(
+                    dependency.interface,
+                    &mock_name,
+                    &MockOptions::default(),
+                );
+                if !mock_definitions.contains(&mock_def) {
+                    mock_definitions.push(mock_def);
+                }
+            }
+        }
+
+        let fixture_name = fix.fixture.unwrap_or(format!("{}Test", class_name));
+        let fixture_def =
+            generate_fixture_definition(class, constructor, &fixture_name, |interface| {
+                let mock_name = format!("{}Mock", interface.get_name().unwrap());
+                find_class_entity(&self.tu, &mock_name).map_or(mock_name, get_qualified_name)
+            });
+
+        let class_header = class
+            .get_location()
+            .and_then(|loc| loc.get_file_location().file)
+            .map(|file| file.get_path());
+
+        let output = format!(
+            "#include <gmock/gmock.h>\n#include <gtest/gtest.h>\n\n{}#include <memory>\n\n{}{}\n",
+            class_header.map_or(String::new(), |path| format!(
+                "#include \"{}\"\n\n",
+                get_file_name(path.to_str().unwrap())
+            )),
+            mock_definitions
+                .iter()
+                .map(|def| format!("{}\n\n", def))
+                .collect::<String>(),
+            fixture_def
+        );
+
+        write_output(fix.output.as_deref(), &output)
+    }
+
+    pub fn run_expect(&self, exp: ExpectOpts) -> CLIResult<()> {
+        let interface_name = exp
+            .interface
+            .as_ref()
+            .map_or(get_file_stem(&exp.interface_source), String::as_str);
+        let class = self.find_interface(interface_name, exp.interface.is_none())?;
+
+        let snippets =
+            generate_expect_call_snippets(class, &exp.mock_variable, exp.method.as_deref());
+        match (snippets.is_empty(), exp.method) {
+            (true, Some(method_name)) => {
+                Err(MethodNotFound(interface_name.to_string(), method_name))
+            }
+            _ => {
+                snippets.iter().for_each(|snippet| println!("{}", snippet));
+                Ok(())
+            }
+        }
+    }
+
+    pub fn run_extract_interface(&self, ext: ExtractInterfaceOpts) -> CLIResult<()> {
+        let class_name = ext
+            .class
+            .as_ref()
+            .map_or(get_file_stem(&ext.source), String::as_str);
+        let class = self.find_class(class_name, ConcreteClassNotFound)?;
+
+        write_output(
+            ext.output.as_deref(),
+            &generate_interface_definition(class, &ext.interface),
+        )?;
+
+        if let Some(patch) = ext.patch {
+            let class_file = class
+                .get_location()
+                .and_then(|loc| loc.get_file_location().file)
+                .unwrap()
+                .get_path();
+            let original = std::fs::read_to_string(&class_file).map_err(SourceFileNotFound)?;
+            let header = match &ext.output {
+                Some(output) => std::fs::canonicalize(output)
+                    .map(|output| get_relative_path(&output, class_file.parent().unwrap()))
+                    .unwrap_or_else(|_| PathBuf::from(output)),
+                None => PathBuf::from(format!("{}.h", ext.interface)),
+            };
+            let mut insertions = get_derivation_insertions(class, &ext.interface);
+            insertions.push(get_include_insertion(
+                &original,
+                &header.to_str().unwrap().replace('\\', "/"),
+            ));
+            let patched = apply_insertions(&original, &insertions);
+            let relative_path = std::env::current_dir()
+                .and_then(std::fs::canonicalize)
+                .map(|dir| get_relative_path(&class_file, &dir))
+                .unwrap_or(class_file);
+            write_output(
+                Some(&patch),
+                &format_unified_diff(relative_path.to_str().unwrap(), &original, &patched),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Looks up the definition of an interface class like `find_class`, suggesting similarly named
+    /// classes if it cannot be found. `inferred` indicates whether the name was inferred from the
+    /// source file name rather than given explicitly.
+    fn find_interface(&self, interface_name: &str, inferred: bool) -> CLIResult<Entity<'_>> {
+        self.find_class(interface_name, |name| {
+            InterfaceClassNotFound(
+                name,
+                suggest_class_names(&self.tu, interface_name),
+                inferred,
+            )
+        })
+    }
+
+    /// Fails if errors while parsing the source file may have corrupted the declaration of the
+    /// class or of any of its base classes, whose methods are mocked as well, unless
+    /// `--ignore-parse-errors` is given.
+    fn check_parse_errors(&self, class: Entity) -> CLIResult<()> {
+        let count = self
+            .tu
+            .get_diagnostics()
+            .iter()
+            .filter(|diagnostic| affects_class(diagnostic, class))
+            .count();
+        if count > 0 && !self.ignore_parse_errors {
+            return Err(ParseErrors(get_qualified_name(class), count));
+        }
+        Ok(())
+    }
+
+    /// Looks up the definition of a class by its (possibly qualified) name, failing if there is no
+    /// such class, if the name is ambiguous, or if the class is merely forward-declared.
+    fn find_class(
+        &self,
+        class_name: &str,
+        not_found: impl FnOnce(String) -> CLIError,
+    ) -> CLIResult<Entity<'_>> {
+        match &find_class_entities(&self.tu, class_name)[..] {
+            [] => Err(not_found(class_name.to_string())),
+            [class] if class.is_definition() => {
+                self.check_parse_errors(*class)?;
+                Ok(*class)
+            }
+            [class] => Err(IncompleteClass(
+                class_name.to_string(),
+                format_candidate(*class),
+            )),
+            candidates => Err(AmbiguousClassName(
+                class_name.to_string(),
+                candidates
+                    .iter()
+                    .map(|&class| format_candidate(class))
+                    .collect(),
+            )),
+        }
+    }
+
     pub fn run_dump(&self, dmp: DumpOpts) -> CLIResult<()> {
         let entity = dmp
             .class
@@ -100,13 +581,103 @@ impl<'i> MockeryApp<'i> {
     }
 }
 
-fn find_compilation_database(starting_point: &Path, radius: usize) -> CLIResult<PathBuf> {
-    FilesystemDirectoryNode {
-        path: std::fs::canonicalize(starting_point).map_err(|e| {
-            CompilationDatabaseSearchStartingPointNotFound(starting_point.to_path_buf(), e)
-        })?,
+fn get_file_stem(path: &str) -> &str {
+    Path::new(path)
+        .file_stem()
+        .and_then(std::ffi::OsStr::to_str)
+        .unwrap()
+}
+
+fn get_file_name(path: &str) -> &str {
+    Path::new(path)
+        .file_name()
+        .and_then(std::ffi::OsStr::to_str)
+        .unwrap()
+}
+
+fn format_candidate(class: Entity) -> String {
+    let location = class.get_location().map(|loc| loc.get_file_location());
+    match location.and_then(|loc| loc.file.map(|file| (file.get_path(), loc.line))) {
+        Some((path, line)) => format!(
+            "{} at {}:{}",
+            get_qualified_name(class),
+            path.to_str().unwrap(),
+            line
+        ),
+        None => get_qualified_name(class),
+    }
+}
+
+fn write_output(output: Option<&str>, contents: &str) -> CLIResult<()> {
+    match output {
+        Some(path) => std::fs::write(path, contents)
+            .map_err(|e| OutputFileNotWritable(PathBuf::from(path), e)),
+        None => {
+            println!("{}", contents);
+            Ok(())
+        }
+    }
+}
+
+/// Searches for a compile commands database within the given radius around the source file. Only
+/// the closest candidates are considered; among these, databases which apply to the source file
+/// are preferred, followed by those in conventional build directories.
+fn find_compilation_database(
+    source_file: &Path,
+    radius: usize,
+    verbose: bool,
+) -> CLIResult<PathBuf> {
+    let starting_point = source_file.parent().unwrap();
+    let starting_point = std::fs::canonicalize(starting_point).map_err(|e| {
+        CompilationDatabaseSearchStartingPointNotFound(starting_point.to_path_buf(), e)
+    })?;
+    let mut candidates = FilesystemDirectoryNode {
+        path: starting_point.clone(),
     }
     .search(radius)
-    .find(|path| path.join("compile_commands.json").exists())
-    .ok_or(CompilationDatabaseSearchFailed)
+    .filter(|path| has_compilation_database(path))
+    .map(|path| (get_directory_distance(&starting_point, &path), path))
+    .peekable();
+    let distance = match candidates.peek() {
+        Some(&(distance, _)) => distance,
+        None => return Err(CompilationDatabaseSearchFailed),
+    };
+    let candidates: Vec<_> = candidates
+        .take_while(|&(candidate_distance, _)| candidate_distance == distance)
+        .map(|(_, path)| {
+            let applies = has_compile_commands_for(&path, source_file);
+            let build_directory = is_build_directory(&path);
+            (path, applies, build_directory)
+        })
+        .collect();
+
+    if verbose {
+        eprintln!(
+            "Found {} compile commands database(s) at distance {}:",
+            candidates.len(),
+            distance
+        );
+        for (path, applies, build_directory) in &candidates {
+            eprintln!(
+                "\t{}{}{}",
+                path.to_str().unwrap(),
+                if *applies {
+                    " (lists the source file)"
+                } else {
+                    ""
+                },
+                if *build_directory {
+                    " (build directory)"
+                } else {
+                    ""
+                },
+            );
+        }
+    }
+
+    candidates
+        .into_iter()
+        .min_by_key(|&(_, applies, build_directory)| (!applies, !build_directory))
+        .map(|(path, _, _)| path)
+        .ok_or(CompilationDatabaseSearchFailed)
 }
@@ -1,21 +1,102 @@
-use clap::Parser;
+use clap::{ArgEnum, Parser};
 
 /// A tool for creating Google Mock mock class definitions based on the pure virtual member
 /// functions of an interface class, and for keeping the mock up-to-date as the interface evolves.
 #[derive(Parser)]
 #[clap(version = "0.1.0", author = "Jonas Greitemann <jgreitemann@gmail.com>")]
 pub struct MockeryOpts {
-    /// Path to the compile commands database (`compile_commands.json`). By default, we will try to
-    /// find the database in the vicinity of the provided source file.
+    /// Path to the directory containing the compile commands database (`compile_commands.json`) or
+    /// a `compile_flags.txt` file. By default, we will try to find either in the vicinity of the
+    /// provided source file.
     #[clap(short, long)]
     pub compile_commands: Option<String>,
 
     /// The "search radius" around the provided source file in which the compile commands database
     /// is searched for if the path is not explicitly provided. Relative paths of up to this number
-    /// of levels (either up or down), relative to the source file, are considered.
+    /// of levels (either up or down), relative to the source file, are considered. Hidden and
+    /// git-ignored directories (other than build directories) as well as `node_modules` are
+    /// skipped. Of the closest databases, one listing the source file is preferred, followed by
+    /// those in conventional build directories (`build*`, `cmake-build-*`, `out/*`).
     #[clap(short = 'r', long, default_value = "3")]
     pub search_radius: usize,
 
+    /// The (zero-based) index of the compile command to use, if the database lists several for the
+    /// source file. By default, the first command with which the source file parses without errors
+    /// is used.
+    #[clap(long, value_name = "INDEX")]
+    pub command_index: Option<usize>,
+
+    /// Only consider compile commands one of whose arguments contains the given pattern, e.g. an
+    /// output file or a flag like `-DFEATURE_X`, if the database lists several for the source file.
+    #[clap(long, value_name = "PATTERN", allow_hyphen_values = true)]
+    pub command_match: Option<String>,
+
+    /// An additional argument to append to the compiler command line. Can be used multiple times.
+    #[clap(
+        long,
+        multiple_occurrences(true),
+        allow_hyphen_values = true,
+        value_name = "ARG"
+    )]
+    pub extra_arg: Vec<String>,
+
+    /// An additional argument to prepend to the compiler command line. Can be used multiple times.
+    #[clap(
+        long,
+        multiple_occurrences(true),
+        allow_hyphen_values = true,
+        value_name = "ARG"
+    )]
+    pub extra_arg_before: Vec<String>,
+
+    /// Remove the arguments matching the pattern from the compiler command line, e.g. flags which
+    /// are specific to GCC. A `*` in the pattern matches any sequence of characters, e.g.
+    /// `-fno-tree-*`. Options taking a separate value, such as `-include-pch <FILE>`, are removed
+    /// along with the value. Can be used multiple times.
+    #[clap(
+        long,
+        multiple_occurrences(true),
+        allow_hyphen_values = true,
+        value_name = "PATTERN"
+    )]
+    pub remove_arg: Vec<String>,
+
+    /// Pattern of compiler executables (e.g. `/usr/bin/*g++*`) which may be run to query their
+    /// system include directories and resource directory. By default, the compiler named in the
+    /// compile commands database is queried, and the results are added to the arguments so that
+    /// the standard library headers are found even if the compiler differs from libclang. When
+    /// given, only compilers matching one of the patterns are run. A `*` matches any sequence of
+    /// characters. The results of the query are cached in `$XDG_CACHE_HOME/mockery`. Can be used
+    /// multiple times.
+    #[clap(long, multiple_occurrences(true), value_name = "PATTERN")]
+    pub query_driver: Vec<String>,
+
+    /// Do not run the compiler named in the compile commands database to query its system include
+    /// directories, even if it matches `--query-driver`.
+    #[clap(long)]
+    pub no_system_include_detection: bool,
+
+    /// Generate code even if the declarations of the classes involved are affected by errors while
+    /// parsing the source file. Such errors are reported in any case, but may cause parts of the
+    /// declarations to be missing from the generated code.
+    #[clap(long)]
+    pub ignore_parse_errors: bool,
+
+    /// The language standard (e.g. `c++17`) with which the source file is parsed in the absence of
+    /// a compile commands database or `compile_flags.txt`.
+    #[clap(long, value_name = "STANDARD")]
+    pub std: Option<String>,
+
+    /// An include directory which is searched when parsing the source file in the absence of a
+    /// compile commands database or `compile_flags.txt`. Can be used multiple times.
+    #[clap(
+        short = 'I',
+        long = "include-directory",
+        multiple_occurrences(true),
+        value_name = "DIR"
+    )]
+    pub include_directories: Vec<String>,
+
     /// A level of verbosity; can be used multiple times
     #[clap(short, long, parse(from_occurrences))]
     pub verbose: u32,
@@ -28,6 +109,10 @@ pub struct MockeryOpts {
 pub enum SubCommand {
     Create(CreateOpts),
     Update(UpdateOpts),
+    Implement(ImplementOpts),
+    Fixture(FixtureOpts),
+    Expect(ExpectOpts),
+    ExtractInterface(ExtractInterfaceOpts),
     Dump(DumpOpts),
 }
 
@@ -38,19 +123,82 @@ pub struct CreateOpts {
     pub interface_source: String,
 
     /// Name of the interface class that is to be mocked. The default is inferred from the filename
-    /// of the source translation unit.
-    #[clap(short, long)]
-    pub interface: Option<String>,
+    /// of the source translation unit. Can be used multiple times to mock several interfaces from
+    /// the same translation unit.
+    #[clap(short, long, multiple_occurrences(true))]
+    pub interface: Vec<String>,
+
+    /// Create mocks for all abstract classes declared in the translation unit, i.e. all classes
+    /// with at least one unimplemented pure virtual member function. Classes declared in system
+    /// headers are ignored.
+    #[clap(long, conflicts_with_all = &["interface", "mock"])]
+    pub all: bool,
 
     /// Name which is given to the resulting mock class. By default, the interface class's name is
-    /// suffixed with `Mock`.
-    #[clap(short, long)]
-    pub mock: Option<String>,
+    /// suffixed with `Mock` (or `Spy` for the spy backend). When multiple interfaces are given, the
+    /// n-th mock name applies to the n-th interface.
+    #[clap(short, long, multiple_occurrences(true))]
+    pub mock: Vec<String>,
+
+    /// The kind of test double to generate: a Google Mock class, or a recording spy which does not
+    /// depend on gMock and merely records the arguments of each call and returns canned values.
+    #[clap(long, arg_enum, default_value = "gmock")]
+    pub backend: Backend,
+
+    /// Generate a mock even if the interface class has no unimplemented pure virtual member
+    /// functions, in which case the mock would be empty. A warning is emitted instead of an error.
+    #[clap(long)]
+    pub allow_empty: bool,
+
+    /// Emit a constructor which sets up a default action using `ON_CALL` returning the given value
+    /// for all mocked methods with the given return type, e.g. `--default-return bool=true`. gMock
+    /// already returns `false`, `0`, `nullptr` or a value-initialized object by default, so only
+    /// values deviating from these, or values of types which are not default-constructible, need to
+    /// be given. Can be used multiple times.
+    #[clap(long, multiple_occurrences(true), parse(try_from_str = parse_key_value))]
+    pub default_return: Vec<(String, String)>,
+
+    /// Name of a fake class implementing the interface. The mock will hold an instance of the fake
+    /// and get a `DelegateToFake()` member which makes all mocked methods delegate to the fake by
+    /// default.
+    #[clap(long, value_name = "FAKE_CLASS")]
+    pub delegate_to: Option<String>,
+
+    /// For mocked methods taking a `std::function` callback, capture the last callback that was
+    /// passed and emit a helper to invoke it, as well as a companion `testing::MockFunction` member
+    /// with the callback's signature, on which each invocation through the helper is reported.
+    #[clap(long)]
+    pub mock_callbacks: bool,
+
+    /// Emit `PrintTo` overloads for the user-defined aggregate types used as parameter or return
+    /// types of the mocked methods which lack them, so that gMock failure messages show their
+    /// fields rather than raw bytes.
+    #[clap(long)]
+    pub print_to: bool,
+
+    /// Emit `operator==` for the user-defined aggregate types used as parameter or return types of
+    /// the mocked methods which lack it.
+    #[clap(long)]
+    pub equality: bool,
+
+    /// Emit an alias `Nice<mock> = ::testing::NiceMock<mock>` alongside the mock class.
+    #[clap(long)]
+    pub nice: bool,
+
+    /// Emit an alias `Strict<mock> = ::testing::StrictMock<mock>` alongside the mock class.
+    #[clap(long)]
+    pub strict: bool,
 
     /// Path to the file which the mock class definition should be written to. If the file already
-    /// exists, it will be overwritten!
-    #[clap(short, long)]
-    pub output: Option<String>,
+    /// exists, it will be overwritten! When multiple interfaces are given, either a single output
+    /// receives all mocks, or one output per interface has to be given.
+    #[clap(short, long, multiple_occurrences(true))]
+    pub output: Vec<String>,
+
+    /// Path to a directory into which each mock class definition is written as a separate header,
+    /// named after the mock class. Existing files will be overwritten!
+    #[clap(long, conflicts_with_all = &["output", "stdout"])]
+    pub output_dir: Option<String>,
 
     /// Force the mock class definition to be written to stdout. This is the default behavior in the
     /// absence of `--output`; `--stdout` can be used to retain this behavior even in the presence
@@ -59,6 +207,18 @@ pub struct CreateOpts {
     pub stdout: bool,
 }
 
+fn parse_key_value(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .ok_or(format!("expected `<TYPE>=<VALUE>`, got `{}`", s))
+}
+
+#[derive(ArgEnum, Clone, Copy, PartialEq)]
+pub enum Backend {
+    Gmock,
+    Spy,
+}
+
 /// Modify an existing mock class definition to mirror changes to the underlying interface class.
 #[derive(Parser)]
 pub struct UpdateOpts {
@@ -86,6 +246,108 @@ pub struct UpdateOpts {
     pub patch: Option<String>,
 }
 
+/// Create a stub of a concrete class implementing an interface class, consisting of a header with
+/// the declarations of all overrides and a source file with their out-of-line definitions.
+#[derive(Parser)]
+pub struct ImplementOpts {
+    /// Path to a translation unit (*.cpp) which includes the interface that is to be implemented.
+    pub interface_source: String,
+
+    /// Name of the interface class that is to be implemented. The default is inferred from the
+    /// filename of the source translation unit.
+    #[clap(short, long)]
+    pub interface: Option<String>,
+
+    /// Name which is given to the implementing class.
+    #[clap(long)]
+    pub class: String,
+
+    /// Path to the file which the header with the class definition should be written to. If the
+    /// file already exists, it will be overwritten! By default, the header is written to stdout.
+    #[clap(long)]
+    pub header: Option<String>,
+
+    /// Path to the file which the source with the out-of-line member function definitions should be
+    /// written to. If the file already exists, it will be overwritten! By default, the source is
+    /// written to stdout.
+    #[clap(long)]
+    pub source: Option<String>,
+}
+
+/// Create a GoogleTest fixture for a class under test which injects mocks for all of its
+/// dependencies, i.e. the constructor parameters which are (smart) pointers or references to
+/// abstract classes.
+#[derive(Parser)]
+pub struct FixtureOpts {
+    /// Path to a translation unit (*.cpp) which includes the class under test.
+    pub source: String,
+
+    /// Name of the class under test. The default is inferred from the filename of the source
+    /// translation unit.
+    #[clap(long)]
+    pub class: Option<String>,
+
+    /// Name which is given to the fixture. By default, the name of the class under test is
+    /// suffixed with `Test`.
+    #[clap(short, long)]
+    pub fixture: Option<String>,
+
+    /// Path to the file which the fixture (and the definitions of any mocks which do not exist yet)
+    /// should be written to. If the file already exists, it will be overwritten! By default, the
+    /// fixture is written to stdout.
+    #[clap(short, long)]
+    pub output: Option<String>,
+}
+
+/// Print ready-to-edit `EXPECT_CALL` snippets for the methods of an interface class.
+#[derive(Parser)]
+pub struct ExpectOpts {
+    /// Path to a translation unit (*.cpp) which includes the interface.
+    pub interface_source: String,
+
+    /// Name of the interface class. The default is inferred from the filename of the source
+    /// translation unit.
+    #[clap(short, long)]
+    pub interface: Option<String>,
+
+    /// Name of the method for which to print a snippet. By default, snippets for all mocked methods
+    /// of the interface are printed.
+    #[clap(long)]
+    pub method: Option<String>,
+
+    /// Name of the mock object variable used in the snippets.
+    #[clap(long, default_value = "mock")]
+    pub mock_variable: String,
+}
+
+/// Extract an interface from a concrete class, consisting of pure virtual versions of all of its
+/// public non-static member functions.
+#[derive(Parser)]
+pub struct ExtractInterfaceOpts {
+    /// Path to a translation unit (*.cpp) which includes the concrete class.
+    pub source: String,
+
+    /// Name of the concrete class from which the interface is extracted. The default is inferred
+    /// from the filename of the source translation unit.
+    #[clap(long)]
+    pub class: Option<String>,
+
+    /// Name which is given to the extracted interface class.
+    #[clap(short, long)]
+    pub interface: String,
+
+    /// Path to the file which the interface class definition should be written to. If the file
+    /// already exists, it will be overwritten! By default, the interface is written to stdout.
+    #[clap(short, long)]
+    pub output: Option<String>,
+
+    /// Format a patch which makes the concrete class derive from the extracted interface, includes
+    /// the header of the interface (the `--output` file, or else `<INTERFACE>.h`) and marks the
+    /// extracted member functions as `override`, and write it to the specified file.
+    #[clap(short, long)]
+    pub patch: Option<String>,
+}
+
 /// Dump the AST for the specified source file or class.
 #[derive(Parser)]
 pub struct DumpOpts {
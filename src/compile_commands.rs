@@ -0,0 +1,794 @@
+use clang::*;
+use std::path::{Path, PathBuf};
+
+/// Maximum number of translation units which are parsed in search of one including a header.
+const MAX_INCLUDER_CANDIDATES: usize = 8;
+
+pub const COMPILE_COMMANDS_JSON: &str = "compile_commands.json";
+pub const COMPILE_FLAGS_TXT: &str = "compile_flags.txt";
+
+/// Arguments which are always removed since they confuse libclang: `cl`'s per-file language
+/// overrides, which refer to the source file that libclang passes itself.
+const DEFAULT_REMOVED_ARGUMENTS: &[&str] = &["/Tc", "/Tp"];
+
+/// `cl`'s language overrides for all source files, which are only understood in cl driver mode and
+/// are removed otherwise, e.g. when a wrapped `cl` was not recognized as such.
+const CL_LANGUAGE_ARGUMENTS: &[&str] = &["/TC", "/TP"];
+
+/// Maximum depth of nested response files, guarding against cycles.
+const MAX_RESPONSE_FILE_DEPTH: usize = 16;
+
+/// Options taking a path, either as a separate argument or joined to the option itself, which need
+/// to be resolved against the working directory of the compile command. Longer options precede
+/// their prefixes.
+const PATH_OPTIONS: &[&str] = &[
+    "-include-pch",
+    "-include",
+    "-imacros",
+    "-isystem",
+    "-isysroot",
+    "-iquote",
+    "-idirafter",
+    "-iframework",
+    "-ivfsoverlay",
+    "--sysroot=",
+    "--sysroot",
+    "-I",
+];
+
+/// Additional options taking a path which are only recognized by `cl`.
+const CL_PATH_OPTIONS: &[&str] = &["/external:I", "/FI", "/I", "-imsvc"];
+
+/// Options besides [PATH_OPTIONS] which take a value that may be passed as a separate argument.
+const VALUE_OPTIONS: &[&str] = &[
+    "-D", "-U", "-x", "-o", "-MF", "-MT", "-MQ", "-Xclang", "-target", "-arch",
+];
+
+const HEADER_EXTENSIONS: &[&str] = &["h", "hh", "hpp", "hxx", "h++", "inl", "ipp", "tpp"];
+
+/// The arguments with which a source file is parsed, as derived from a compile command.
+#[derive(Clone)]
+pub struct CompileArguments {
+    pub filename: PathBuf,
+    pub arguments: Vec<String>,
+
+    /// The compiler executable of the compile command, if any. A relative path is resolved against
+    /// the working directory of the command, while a bare name is left to be looked up on the
+    /// `PATH`.
+    pub compiler: Option<String>,
+
+    /// Whether the arguments are meant for MSVC's `cl.exe` or `clang-cl`, rather than a
+    /// GCC-compatible compiler driver.
+    pub cl_driver: bool,
+}
+
+impl CompileArguments {
+    /// The language of the source file for the purpose of querying the compiler, i.e. `c` or
+    /// `c++`.
+    pub fn get_language(&self) -> &'static str {
+        match self.filename.extension().and_then(std::ffi::OsStr::to_str) {
+            Some("c") => "c",
+            _ => "c++",
+        }
+    }
+
+    /// The arguments to pass to libclang, which appends the source file itself. `cl`-style
+    /// arguments are parsed in `cl` driver mode, and the source file is separated from them lest
+    /// an absolute path like `/Users/...` be taken for an option.
+    pub fn get_parser_arguments(&self) -> Vec<String> {
+        if self.cl_driver {
+            std::iter::once("--driver-mode=cl".to_string())
+                .chain(self.arguments.iter().cloned())
+                .chain(std::iter::once("--".to_string()))
+                .collect()
+        } else {
+            self.arguments.clone()
+        }
+    }
+}
+
+/// Extracts the arguments from a compile command, omitting the compiler executable (which libclang
+/// supplies itself) and the source file. Response files and relative paths are resolved against
+/// the command's working directory.
+pub fn get_compile_arguments(command: &CompileCommand) -> CompileArguments {
+    let directory = command.get_directory();
+    let filename = directory.join(command.get_filename());
+    let mut arguments = command.get_arguments();
+    let compiler = if arguments.is_empty() {
+        String::new()
+    } else {
+        arguments.remove(0)
+    };
+    let arguments: Vec<_> =
+        expand_response_files(arguments, &directory, is_cl_driver(&compiler), 0)
+            .into_iter()
+            .filter(|a| directory.join(a) != filename)
+            .collect();
+    let cl_driver = is_cl_driver(&compiler) || arguments.iter().any(|a| a == "--driver-mode=cl");
+    let arguments = resolve_relative_paths(arguments, &directory, cl_driver);
+    let compiler = if Path::new(&compiler).components().count() > 1 {
+        directory.join(&compiler).to_string_lossy().into_owned()
+    } else {
+        compiler
+    };
+    CompileArguments {
+        filename,
+        arguments,
+        compiler: Some(compiler).filter(|compiler| !compiler.is_empty()),
+        cl_driver,
+    }
+}
+
+/// Resolves the relative paths passed to options like `-I` against the given directory, such that
+/// the arguments do not depend on the working directory of the process.
+pub fn resolve_relative_paths(
+    arguments: Vec<String>,
+    directory: &Path,
+    cl_driver: bool,
+) -> Vec<String> {
+    let options: Vec<_> = if cl_driver {
+        PATH_OPTIONS.iter().chain(CL_PATH_OPTIONS).collect()
+    } else {
+        PATH_OPTIONS.iter().collect()
+    };
+    let resolve = |path: &str| directory.join(path).to_string_lossy().into_owned();
+
+    let mut resolved = Vec::with_capacity(arguments.len());
+    let mut arguments = arguments.into_iter().peekable();
+    while let Some(mut arg) = arguments.next() {
+        // Options passed on to the frontend, including their separate value, are prefixed by
+        // `-Xclang` each.
+        let prefixed = arg == "-Xclang";
+        if prefixed {
+            match arguments.next() {
+                Some(option) => resolved.push(std::mem::replace(&mut arg, option)),
+                None => {
+                    resolved.push(arg);
+                    break;
+                }
+            }
+        }
+        match options.iter().find(|option| arg.starts_with(**option)) {
+            Some(option) if arg.len() == option.len() => {
+                resolved.push(arg);
+                if prefixed {
+                    resolved.extend(arguments.next_if(|next| next == "-Xclang"));
+                }
+                resolved.extend(arguments.next().map(|path| resolve(&path)));
+            }
+            Some(option) => resolved.push(format!("{}{}", option, resolve(&arg[option.len()..]))),
+            None => resolved.push(arg),
+        }
+    }
+    resolved
+}
+
+/// Replaces `@file` arguments by the contents of the response file, split into arguments using
+/// shell-style quoting (or Windows-style quoting for `cl`). Relative paths are resolved against the
+/// given directory. Response files which cannot be read are passed on as is.
+fn expand_response_files(
+    arguments: Vec<String>,
+    directory: &Path,
+    windows_quoting: bool,
+    depth: usize,
+) -> Vec<String> {
+    arguments
+        .into_iter()
+        .flat_map(|arg| {
+            let contents = arg
+                .strip_prefix('@')
+                .filter(|_| depth < MAX_RESPONSE_FILE_DEPTH)
+                .and_then(|path| std::fs::read_to_string(directory.join(path)).ok());
+            match contents {
+                Some(contents) => expand_response_files(
+                    split_arguments(&contents, windows_quoting),
+                    directory,
+                    windows_quoting,
+                    depth + 1,
+                ),
+                None => vec![arg],
+            }
+        })
+        .collect()
+}
+
+/// Splits the contents of a response file into arguments at unquoted whitespace. Within single
+/// quotes, all characters are literal; elsewhere, a backslash escapes the next character. With
+/// Windows-style quoting, single quotes are literal and backslashes only escape double quotes.
+fn split_arguments(contents: &str, windows_quoting: bool) -> Vec<String> {
+    let mut arguments = Vec::new();
+    let mut current: Option<String> = None;
+    let mut quote = None;
+    let mut chars = contents.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quote) {
+            ('\\', Some('\'')) => current.get_or_insert_with(String::new).push(c),
+            ('\\', _) if !windows_quoting || chars.peek() == Some(&'"') => {
+                let escaped = chars.next().unwrap_or(c);
+                current.get_or_insert_with(String::new).push(escaped);
+            }
+            ('"', None) => {
+                quote = Some(c);
+                current.get_or_insert_with(String::new);
+            }
+            ('\'', None) if !windows_quoting => {
+                quote = Some(c);
+                current.get_or_insert_with(String::new);
+            }
+            (c, Some(q)) if c == q => quote = None,
+            (c, None) if c.is_whitespace() => arguments.extend(current.take()),
+            (c, _) => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+    arguments.extend(current);
+    arguments
+}
+
+/// Whether the compiler executable is MSVC's `cl.exe` or `clang-cl`, judging by its name. Windows
+/// paths are supported regardless of the host platform.
+fn is_cl_driver(compiler: &str) -> bool {
+    let name = compiler
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or(compiler)
+        .to_lowercase();
+    let name = name.strip_suffix(".exe").unwrap_or(&name);
+    name == "cl" || name.starts_with("clang-cl")
+}
+
+/// Arguments which apply to any source file alike, e.g. those from `compile_flags.txt`.
+pub fn get_fixed_arguments(source_file: &Path, flags: Vec<String>) -> CompileArguments {
+    let language = if is_header(source_file) {
+        Some(get_header_language_argument(source_file, false))
+    } else {
+        None
+    };
+    CompileArguments {
+        filename: source_file.to_path_buf(),
+        arguments: flags.into_iter().chain(language).collect(),
+        compiler: None,
+        cl_driver: false,
+    }
+}
+
+/// Adds extra arguments before and after the existing ones, and removes those arguments matching
+/// any of the given patterns, in which `*` stands for any sequence of characters. Removing an
+/// option also removes its separate value, and `-Xclang` prefixes of either.
+pub fn adjust_arguments(
+    compile_args: CompileArguments,
+    extra_before: &[String],
+    extra_after: &[String],
+    removed: &[String],
+) -> CompileArguments {
+    let cl_driver = compile_args.cl_driver;
+    let is_removed = |arg: &str| {
+        DEFAULT_REMOVED_ARGUMENTS.contains(&arg)
+            || (!cl_driver && CL_LANGUAGE_ARGUMENTS.contains(&arg))
+            || removed.iter().any(|pattern| matches_pattern(arg, pattern))
+    };
+
+    let arguments = &compile_args.arguments;
+    let mut kept = extra_before.to_vec();
+    let mut i = 0;
+    while i < arguments.len() {
+        let prefixed = arguments[i] == "-Xclang" && i + 1 < arguments.len();
+        let option = if prefixed { i + 1 } else { i };
+        if !is_removed(&arguments[option]) {
+            kept.push(arguments[i].clone());
+            i += 1;
+            continue;
+        }
+        i = option + 1;
+        if takes_separate_value(&arguments[option], cl_driver) {
+            if prefixed && arguments.get(i).map(String::as_str) == Some("-Xclang") {
+                i += 1;
+            }
+            i += 1;
+        }
+    }
+    kept.extend(extra_after.iter().cloned());
+
+    CompileArguments {
+        arguments: kept,
+        ..compile_args
+    }
+}
+
+fn takes_separate_value(option: &str, cl_driver: bool) -> bool {
+    (PATH_OPTIONS.contains(&option) && !option.ends_with('='))
+        || VALUE_OPTIONS.contains(&option)
+        || (cl_driver && CL_PATH_OPTIONS.contains(&option))
+}
+
+pub fn matches_pattern(arg: &str, pattern: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let mut rest = match arg.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let mut parts: Vec<_> = parts.collect();
+    let last = match parts.pop() {
+        Some(last) => last,
+        None => return rest.is_empty(),
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// Narrows down the compile commands listed for a source file to those selected by their
+/// (zero-based) index and/or by a pattern which one of their arguments has to contain. Each of the
+/// remaining commands is returned alongside its index.
+pub fn select_compile_arguments(
+    candidates: Vec<CompileArguments>,
+    index: Option<usize>,
+    pattern: Option<&str>,
+) -> Vec<(usize, CompileArguments)> {
+    candidates
+        .into_iter()
+        .enumerate()
+        .filter(|&(i, _)| index.is_none() || index == Some(i))
+        .filter(|(_, args)| match pattern {
+            Some(pattern) => args.arguments.iter().any(|arg| arg.contains(pattern)),
+            None => true,
+        })
+        .collect()
+}
+
+/// Parses the contents of a `compile_flags.txt` file, which lists one argument per line.
+pub fn parse_compile_flags(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+pub fn has_compilation_database(dir: &Path) -> bool {
+    dir.join(COMPILE_COMMANDS_JSON).exists() || dir.join(COMPILE_FLAGS_TXT).exists()
+}
+
+/// Whether the compile commands database in the directory applies to the source file, i.e. it
+/// lists a command for it or, in case of a `compile_flags.txt`, the file lies within the directory.
+pub fn has_compile_commands_for(dir: &Path, source_file: &Path) -> bool {
+    if dir.join(COMPILE_COMMANDS_JSON).exists() {
+        CompilationDatabase::from_directory(dir)
+            .and_then(|db| db.get_compile_commands(source_file))
+            .map(|commands| !commands.get_commands().is_empty())
+            .unwrap_or(false)
+    } else {
+        source_file.starts_with(dir)
+    }
+}
+
+/// Whether the directory follows one of the conventional naming schemes of build directories,
+/// i.e. `build*`, `cmake-build-*` or `out/*`.
+pub fn is_build_directory(dir: &Path) -> bool {
+    let name = |path: Option<&Path>| {
+        path.and_then(Path::file_name)
+            .and_then(std::ffi::OsStr::to_str)
+            .unwrap_or_default()
+            .to_lowercase()
+    };
+    let dir_name = name(Some(dir));
+    dir_name.starts_with("build")
+        || dir_name.starts_with("cmake-build-")
+        || name(dir.parent()) == "out"
+}
+
+/// Whether the directory is a build directory or conventionally holds build directories, like
+/// `out`.
+pub fn may_contain_build_directory(dir: &Path) -> bool {
+    is_build_directory(dir)
+        || dir
+            .file_name()
+            .and_then(std::ffi::OsStr::to_str)
+            .map(str::to_lowercase)
+            .as_deref()
+            == Some("out")
+}
+
+/// The number of levels (up and down) between two directories.
+pub fn get_directory_distance(from: &Path, to: &Path) -> usize {
+    let common = from
+        .components()
+        .zip(to.components())
+        .take_while(|(lhs, rhs)| lhs == rhs)
+        .count();
+    from.components().count() + to.components().count() - 2 * common
+}
+
+pub fn is_header(path: &Path) -> bool {
+    match path.extension().and_then(std::ffi::OsStr::to_str) {
+        Some(ext) => HEADER_EXTENSIONS.contains(&ext),
+        None => true,
+    }
+}
+
+/// Derives the arguments for parsing a header which has no compile command of its own by
+/// borrowing those of a source file from the database. Preferably, this is a source file whose
+/// translation unit (transitively) includes the header; otherwise, the source file closest to the
+/// header is used. The candidates are parsed with the arguments as modified by `adjust`, i.e. as
+/// they would be for parsing the header, whereas the arguments are returned unmodified.
+pub fn infer_header_arguments<F: Fn(CompileArguments) -> CompileArguments>(
+    index: &Index,
+    compile_db: &CompilationDatabase,
+    header: &Path,
+    adjust: F,
+) -> Option<CompileArguments> {
+    let all_commands = compile_db.get_all_compile_commands();
+    let mut candidates: Vec<_> = all_commands
+        .get_commands()
+        .iter()
+        .map(get_compile_arguments)
+        .filter(|args| !is_header(&args.filename))
+        .collect();
+    candidates.sort_by_key(|args| std::cmp::Reverse(get_proximity(&args.filename, header)));
+
+    if candidates.is_empty() {
+        return None;
+    }
+
+    let includer = candidates
+        .iter()
+        .take(MAX_INCLUDER_CANDIDATES)
+        .position(|args| {
+            match index
+                .parser(&args.filename)
+                .arguments(&adjust(args.clone()).get_parser_arguments())
+                .skip_function_bodies(true)
+                .parse()
+            {
+                Ok(tu) => tu.get_file(header).is_some(),
+                Err(_) => false,
+            }
+        })
+        .unwrap_or(0);
+    let source = candidates.swap_remove(includer);
+
+    let language = get_header_language_argument(&source.filename, source.cl_driver);
+    Some(CompileArguments {
+        filename: header.to_path_buf(),
+        arguments: source
+            .arguments
+            .into_iter()
+            .chain(std::iter::once(language))
+            .collect(),
+        compiler: source.compiler,
+        cl_driver: source.cl_driver,
+    })
+}
+
+/// Headers are parsed as C++ unless the arguments were borrowed from a C source file.
+fn get_header_language_argument(source: &Path, cl_driver: bool) -> String {
+    let is_c = source.extension().and_then(std::ffi::OsStr::to_str) == Some("c");
+    match (is_c, cl_driver) {
+        (true, true) => "/TC",
+        (false, true) => "/TP",
+        (true, false) => "-xc-header",
+        (false, false) => "-xc++-header",
+    }
+    .to_string()
+}
+
+/// Ranks how close a source file is to a header: primarily by the number of leading path
+/// components they share, and secondarily by whether their file stems coincide.
+fn get_proximity(source: &Path, header: &Path) -> (usize, bool) {
+    let common_components = source
+        .parent()
+        .unwrap_or(source)
+        .components()
+        .zip(header.parent().unwrap_or(header).components())
+        .take_while(|(lhs, rhs)| lhs == rhs)
+        .count();
+    (common_components, source.file_stem() == header.file_stem())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn headers_are_recognized_by_extension() {
+        assert!(is_header(Path::new("/src/Storage.h")));
+        assert!(is_header(Path::new("/src/Storage.hpp")));
+        assert!(is_header(Path::new("/include/vector")));
+        assert!(!is_header(Path::new("/src/Storage.cpp")));
+        assert!(!is_header(Path::new("/src/Storage.c")));
+    }
+
+    fn get_test_candidates() -> Vec<CompileArguments> {
+        ["-DFEATURE_X", "-DFEATURE_Y", "-DFEATURE_X"]
+            .iter()
+            .map(|define| CompileArguments {
+                filename: PathBuf::from("/src/Storage.cpp"),
+                arguments: vec!["-std=c++17".to_string(), define.to_string()],
+                compiler: None,
+                cl_driver: false,
+            })
+            .collect()
+    }
+
+    fn get_selected_indices(index: Option<usize>, pattern: Option<&str>) -> Vec<usize> {
+        select_compile_arguments(get_test_candidates(), index, pattern)
+            .into_iter()
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    #[test]
+    fn compile_commands_are_selected_by_index_and_pattern() {
+        assert_eq!(get_selected_indices(None, None), vec![0, 1, 2]);
+        assert_eq!(get_selected_indices(Some(1), None), vec![1]);
+        assert_eq!(get_selected_indices(None, Some("FEATURE_X")), vec![0, 2]);
+        assert_eq!(get_selected_indices(Some(2), Some("-DFEATURE_X")), vec![2]);
+        assert!(get_selected_indices(Some(1), Some("-DFEATURE_X")).is_empty());
+        assert!(get_selected_indices(Some(3), None).is_empty());
+    }
+
+    #[test]
+    fn patterns_match_whole_arguments_with_wildcards() {
+        assert!(matches_pattern("-fno-tree-vrp", "-fno-tree-vrp"));
+        assert!(!matches_pattern("-fno-tree-vrp", "-fno-tree"));
+        assert!(matches_pattern("-fno-tree-vrp", "-fno-tree-*"));
+        assert!(matches_pattern("-Winvalid-pch", "*pch"));
+        assert!(matches_pattern("-mno-direct-extern-access", "-m*extern*"));
+        assert!(!matches_pattern("-fno-tree-vrp", "-m*"));
+        assert!(!matches_pattern("-ab", "-a*b*b"));
+    }
+
+    #[test]
+    fn removed_options_take_their_values_along() {
+        let compile_args = CompileArguments {
+            filename: PathBuf::from("/src/Storage.cpp"),
+            arguments: [
+                "-O2",
+                "-include-pch",
+                "pch.h.pch",
+                "-Xclang",
+                "-include-pch",
+                "-Xclang",
+                "/build/cmake_pch.hxx.pch",
+                "-Xclang",
+                "-fno-pch-timestamp",
+                "-DFOO",
+            ]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect(),
+            compiler: None,
+            cl_driver: false,
+        };
+        assert_eq!(
+            adjust_arguments(compile_args, &[], &[], &["-include-pch".to_string()]).arguments,
+            vec!["-O2", "-Xclang", "-fno-pch-timestamp", "-DFOO"]
+        );
+    }
+
+    #[test]
+    fn arguments_are_added_and_removed() {
+        let compile_args = CompileArguments {
+            filename: PathBuf::from("/src/Storage.cpp"),
+            arguments: [
+                "-std=c++17",
+                "/Tp",
+                "/TP",
+                "-fno-tree-vrp",
+                "-fno-tree-pre",
+                "-O2",
+            ]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect(),
+            compiler: None,
+            cl_driver: false,
+        };
+        assert_eq!(
+            adjust_arguments(
+                compile_args,
+                &["-Wno-unknown-warning-option".to_string()],
+                &["-DMOCKERY".to_string()],
+                &["-fno-tree-*".to_string()],
+            )
+            .arguments,
+            vec![
+                "-Wno-unknown-warning-option",
+                "-std=c++17",
+                "-O2",
+                "-DMOCKERY"
+            ]
+        );
+    }
+
+    #[test]
+    fn cl_drivers_are_recognized_by_name() {
+        assert!(is_cl_driver("cl"));
+        assert!(is_cl_driver(
+            "C:\\Program Files\\MSVC\\bin\\Hostx64\\x64\\CL.exe"
+        ));
+        assert!(is_cl_driver("/usr/bin/clang-cl-14"));
+        assert!(!is_cl_driver("/usr/bin/clang++"));
+        assert!(!is_cl_driver("/usr/bin/g++"));
+    }
+
+    #[test]
+    fn cl_arguments_are_parsed_in_cl_driver_mode() {
+        let compile_args = CompileArguments {
+            filename: PathBuf::from("/src/Storage.cpp"),
+            arguments: vec!["/std:c++17".to_string(), "/Iinclude".to_string()],
+            compiler: None,
+            cl_driver: true,
+        };
+        assert_eq!(
+            compile_args.get_parser_arguments(),
+            vec!["--driver-mode=cl", "/std:c++17", "/Iinclude", "--"]
+        );
+    }
+
+    #[test]
+    fn relative_paths_are_resolved_against_directory() {
+        let arguments: Vec<_> = [
+            "-Iinclude",
+            "-I",
+            "../common",
+            "-isystem/opt/include",
+            "-include",
+            "pch.h",
+            "--sysroot=sysroot",
+            "/Iinclude",
+            "-DPATH=include",
+            "-Xclang",
+            "-include-pch",
+            "-Xclang",
+            "foo.pch",
+            "-Xclang",
+            "-Iframework",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+        assert_eq!(
+            resolve_relative_paths(arguments.clone(), Path::new("/build"), false),
+            vec![
+                "-I/build/include",
+                "-I",
+                "/build/../common",
+                "-isystem/opt/include",
+                "-include",
+                "/build/pch.h",
+                "--sysroot=/build/sysroot",
+                "/Iinclude",
+                "-DPATH=include",
+                "-Xclang",
+                "-include-pch",
+                "-Xclang",
+                "/build/foo.pch",
+                "-Xclang",
+                "-I/build/framework",
+            ]
+        );
+        assert_eq!(
+            resolve_relative_paths(arguments, Path::new("/build"), true)[7],
+            "/I/build/include"
+        );
+    }
+
+    #[test]
+    fn response_file_contents_are_split_shell_style() {
+        assert_eq!(
+            split_arguments("-DNAME=\"a b\" '-DQUOTE=\"' -I\\ dir\n  -O2 \"\"", false),
+            vec!["-DNAME=a b", "-DQUOTE=\"", "-I dir", "-O2", ""]
+        );
+    }
+
+    #[test]
+    fn response_file_contents_are_split_windows_style() {
+        assert_eq!(
+            split_arguments("/I\"C:\\Program Files\\include\" /DQUOTE=\\\" 'x'", true),
+            vec!["/IC:\\Program Files\\include", "/DQUOTE=\"", "'x'"]
+        );
+    }
+
+    #[test]
+    fn response_files_are_expanded_recursively() {
+        let directory = std::env::temp_dir().join("mockery-response-files");
+        std::fs::create_dir_all(directory.join("CMakeFiles")).unwrap();
+        std::fs::write(
+            directory.join("CMakeFiles/foo.rsp"),
+            "-Iinclude @CMakeFiles/defines.rsp",
+        )
+        .unwrap();
+        std::fs::write(directory.join("CMakeFiles/defines.rsp"), "-DFOO -DBAR").unwrap();
+
+        assert_eq!(
+            expand_response_files(
+                vec![
+                    "-std=c++17".to_string(),
+                    "@CMakeFiles/foo.rsp".to_string(),
+                    "@missing.rsp".to_string(),
+                ],
+                &directory,
+                false,
+                0
+            ),
+            vec!["-std=c++17", "-Iinclude", "-DFOO", "-DBAR", "@missing.rsp"]
+        );
+    }
+
+    #[test]
+    fn build_directories_are_recognized_by_name() {
+        assert!(is_build_directory(Path::new("/project/build")));
+        assert!(is_build_directory(Path::new("/project/build-release")));
+        assert!(is_build_directory(Path::new("/project/cmake-build-debug")));
+        assert!(is_build_directory(Path::new("/project/out/Debug")));
+        assert!(!is_build_directory(Path::new("/project/src")));
+        assert!(!is_build_directory(Path::new("/project/out")));
+        assert!(may_contain_build_directory(Path::new("/project/out")));
+        assert!(may_contain_build_directory(Path::new("/project/build")));
+        assert!(!may_contain_build_directory(Path::new("/project/src")));
+    }
+
+    #[test]
+    fn directory_distance_counts_levels_up_and_down() {
+        assert_eq!(
+            get_directory_distance(Path::new("/project/src"), Path::new("/project/src")),
+            0
+        );
+        assert_eq!(
+            get_directory_distance(Path::new("/project/src/foo"), Path::new("/project")),
+            2
+        );
+        assert_eq!(
+            get_directory_distance(Path::new("/project/src"), Path::new("/project/build/debug")),
+            3
+        );
+    }
+
+    #[test]
+    fn compile_flags_are_read_line_by_line() {
+        assert_eq!(
+            parse_compile_flags("-std=c++17\n\n  -Iinclude  \n-DNDEBUG\n"),
+            vec!["-std=c++17", "-Iinclude", "-DNDEBUG"]
+        );
+    }
+
+    #[test]
+    fn fixed_arguments_parse_headers_as_cpp() {
+        let flags = vec!["-std=c++17".to_string()];
+        assert_eq!(
+            get_fixed_arguments(Path::new("/src/Storage.h"), flags.clone()).arguments,
+            vec!["-std=c++17", "-xc++-header"]
+        );
+        assert_eq!(
+            get_fixed_arguments(Path::new("/src/Storage.cpp"), flags).arguments,
+            vec!["-std=c++17"]
+        );
+    }
+
+    #[test]
+    fn source_with_same_stem_in_same_directory_is_closest() {
+        let header = Path::new("/project/src/net/Storage.h");
+        let mut sources = vec![
+            Path::new("/project/src/Main.cpp"),
+            Path::new("/project/src/net/Client.cpp"),
+            Path::new("/project/test/net/Storage.cpp"),
+            Path::new("/project/src/net/Storage.cpp"),
+        ];
+        sources.sort_by_key(|source| std::cmp::Reverse(get_proximity(source, header)));
+        assert_eq!(
+            sources,
+            vec![
+                Path::new("/project/src/net/Storage.cpp"),
+                Path::new("/project/src/net/Client.cpp"),
+                Path::new("/project/src/Main.cpp"),
+                Path::new("/project/test/net/Storage.cpp"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use clang::diagnostic::{Diagnostic, Severity};
+use clang::source::SourceLocation;
+use clang::*;
+use colored::Colorize;
+use std::iter::once;
+
+use crate::mock_generation::get_all_base_classes;
+
+/// Formats a diagnostic in the style of a compiler, i.e. its location and message, followed by the
+/// offending source line with a caret marking the column.
+pub fn format_diagnostic(diagnostic: &Diagnostic) -> String {
+    let severity = match diagnostic.get_severity() {
+        Severity::Fatal | Severity::Error => "error:".red().bold(),
+        Severity::Warning => "warning:".yellow().bold(),
+        Severity::Note | Severity::Ignored => "note:".bold(),
+    };
+    let location = diagnostic.get_location().get_file_location();
+    match location.file {
+        Some(file) => {
+            let path = file.get_path();
+            // Diagnostics without a specific line are reported at line 0.
+            let snippet = Some(location.line)
+                .filter(|&line| line > 0)
+                .and_then(|line| {
+                    let contents = std::fs::read_to_string(&path).ok()?;
+                    contents
+                        .lines()
+                        .nth(line.saturating_sub(1) as usize)
+                        .map(|line| format_snippet(line, location.column))
+                })
+                .unwrap_or_default();
+            format!(
+                "{}:{}:{}: {} {}{}",
+                path.to_str().unwrap().yellow(),
+                location.line,
+                location.column,
+                severity,
+                diagnostic.get_text(),
+                snippet
+            )
+        }
+        None => format!("{} {}", severity, diagnostic.get_text()),
+    }
+}
+
+/// The source line, followed by a line with a caret below the (1-based) column. Tabs are retained
+/// in the indentation of the caret so that it lines up.
+fn format_snippet(line: &str, column: u32) -> String {
+    let indentation: String = line
+        .chars()
+        .take(column.saturating_sub(1) as usize)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    format!("\n{}\n{}{}", line, indentation, "^".green().bold())
+}
+
+/// Whether an error diagnostic may have corrupted the declarations of any of the given entities.
+/// This is the case for errors located within the entities' extent, as well as for fatal errors
+/// (e.g. a missing include) after which clang stops reporting further errors.
+pub fn affects_entities(diagnostic: &Diagnostic, entities: &[Entity]) -> bool {
+    match diagnostic.get_severity() {
+        Severity::Fatal => true,
+        Severity::Error => entities
+            .iter()
+            .any(|entity| is_within_entity(diagnostic.get_location(), *entity)),
+        _ => false,
+    }
+}
+
+/// Whether an error diagnostic may have corrupted the declaration of the class or of any of its base
+/// classes, whose methods are part of the class's interface as well.
+pub fn affects_class(diagnostic: &Diagnostic, class: Entity) -> bool {
+    let classes: Vec<_> = get_all_base_classes(class).chain(once(class)).collect();
+    affects_entities(diagnostic, &classes)
+}
+
+fn is_within_entity(location: SourceLocation, entity: Entity) -> bool {
+    let location = location.get_file_location();
+    match (entity.get_range(), location.file) {
+        (Some(range), Some(file)) => {
+            let start = range.get_start().get_file_location();
+            let end = range.get_end().get_file_location();
+            start.file.map(|f| f.get_path()) == Some(file.get_path())
+                && start.offset <= location.offset
+                && location.offset <= end.offset
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mock_generation::find_class_entity;
+    use crate::test_utils::*;
+
+    /// Removes the ANSI escape sequences with which the output is colored, if any.
+    fn strip_colors(text: &str) -> String {
+        let mut result = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                chars.by_ref().find(|&c| c == 'm');
+            } else {
+                result.push(c);
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn caret_is_placed_below_column() {
+        assert_eq!(
+            strip_colors(&format_snippet("\tvirtual Foo bar() = 0;", 10)),
+            "\n\tvirtual Foo bar() = 0;\n\t        ^"
+        );
+    }
+
+    #[test]
+    fn errors_within_class_affect_it() {
+        test_tu_from_source(
+            r#"
+                struct Foo {
+                    virtual Unknown foo() = 0;
+                };
+                struct Bar {
+                    virtual void bar() = 0;
+                };
+                Unknown baz();
+            "#,
+            |tu| {
+                let foo = find_class_entity(tu, "Foo").unwrap();
+                let bar = find_class_entity(tu, "Bar").unwrap();
+                let diagnostics = tu.get_diagnostics();
+                assert_eq!(diagnostics.len(), 2);
+                assert!(affects_entities(&diagnostics[0], &[foo]));
+                assert!(!affects_entities(&diagnostics[0], &[bar]));
+                assert!(!affects_entities(&diagnostics[1], &[foo, bar]));
+            },
+        );
+    }
+
+    #[test]
+    fn errors_within_base_classes_affect_derived_classes() {
+        test_tu_from_source(
+            r#"
+                struct Base {
+                    virtual Unknown foo() = 0;
+                };
+                struct Foo : Base {
+                    virtual void bar() = 0;
+                };
+            "#,
+            |tu| {
+                let foo = find_class_entity(tu, "Foo").unwrap();
+                let diagnostics = tu.get_diagnostics();
+                assert_eq!(diagnostics.len(), 1);
+                assert!(!affects_entities(&diagnostics[0], &[foo]));
+                assert!(affects_class(&diagnostics[0], foo));
+            },
+        );
+    }
+}
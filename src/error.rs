@@ -1,3 +1,4 @@
+use crate::mock_generation::UnmockableReason;
 use colored::Colorize;
 use std::fmt::{Display, Formatter};
 use std::path::PathBuf;
@@ -13,8 +14,20 @@ pub enum CLIError {
     CompilationDatabaseSearchFailed,
     CompilationDatabaseSearchStartingPointNotFound(PathBuf, std::io::Error),
     CompileCommandNotFound(PathBuf),
+    NoMatchingCompileCommand(PathBuf, Option<usize>, Option<String>),
     SourceError(clang::SourceError),
-    InterfaceClassNotFound(String),
+    InterfaceClassNotFound(String, Vec<String>, bool),
+    AmbiguousClassName(String, Vec<String>),
+    IncompleteClass(String, String),
+    ParseErrors(String, usize),
+    UnmockableInterface(String, UnmockableReason),
+    MethodNotFound(String, String),
+    ClassUnderTestNotFound(String),
+    ConcreteClassNotFound(String),
+    ConstructorNotFound(String),
+    OutputFileNotWritable(PathBuf, std::io::Error),
+    ArgumentCountMismatch(&'static str, usize, usize),
+    MockNameCollision(String, Vec<String>),
     NotYetImplemented,
 }
 
@@ -34,7 +47,7 @@ impl Display for CLIError {
                 ))
             }
             CLIError::CompilationDatabaseSearchFailed => {
-                f.write_str("Could not find compile commands database within the specified search radius")
+                f.write_str("Could not find compile commands database or `compile_flags.txt` within the specified search radius; use `--std` and `-I` to parse the source file without them")
             }
             CLIError::CompilationDatabaseSearchStartingPointNotFound(starting_point, io_err) => {
                 f.write_fmt(format_args!(
@@ -47,13 +60,119 @@ impl Display for CLIError {
                 "Failed to find compile command for '{}' in database",
                 source_file.to_str().unwrap().yellow(),
             )),
+            CLIError::NoMatchingCompileCommand(source_file, index, pattern) => {
+                f.write_fmt(format_args!(
+                    "None of the compile commands for '{}' in database",
+                    source_file.to_str().unwrap().yellow(),
+                ))?;
+                if let Some(index) = index {
+                    f.write_fmt(format_args!(
+                        " has index {}",
+                        index.to_string().yellow()
+                    ))?;
+                }
+                if let Some(pattern) = pattern {
+                    f.write_fmt(format_args!(
+                        "{} has an argument containing `{}`",
+                        if index.is_some() { " and" } else { "" },
+                        pattern.yellow()
+                    ))?;
+                }
+                Ok(())
+            }
             CLIError::SourceError(source_err) => f.write_fmt(format_args!(
                 "The source file could not be parsed: {}",
                 source_err.to_string().italic(),
             )),
-            CLIError::InterfaceClassNotFound(interface_name) => f.write_fmt(format_args!(
-                "No interface class named `{}` was found in the specified translation unit",
+            CLIError::InterfaceClassNotFound(interface_name, suggestions, inferred) => {
+                f.write_fmt(format_args!(
+                    "No interface class named `{}` was found in the specified translation unit",
+                    interface_name.yellow(),
+                ))?;
+                if *inferred {
+                    f.write_str(
+                        "\n\tnote: the interface name was inferred from the name of the source file; use `--interface` to specify it",
+                    )?;
+                }
+                if !suggestions.is_empty() {
+                    f.write_fmt(format_args!(
+                        "\n\thelp: did you mean {}?",
+                        suggestions
+                            .iter()
+                            .map(|suggestion| format!("`{}`", suggestion.yellow()))
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                    ))?;
+                }
+                Ok(())
+            }
+            CLIError::AmbiguousClassName(class_name, candidates) => f.write_fmt(format_args!(
+                "The class name `{}` is ambiguous; candidates are:{}",
+                class_name.yellow(),
+                candidates
+                    .iter()
+                    .map(|candidate| format!("\n\t{}", candidate))
+                    .collect::<String>(),
+            )),
+            CLIError::IncompleteClass(class_name, declaration) => f.write_fmt(format_args!(
+                "Only a forward declaration of `{}` is visible in the specified translation unit: {}",
+                class_name.yellow(),
+                declaration.italic(),
+            )),
+            CLIError::ParseErrors(class_name, count) => f.write_fmt(format_args!(
+                "The declaration of `{}` is affected by {} parse error(s)\n\thelp: fix the errors or use `--ignore-parse-errors` to generate code anyway",
+                class_name.yellow(),
+                count.to_string().yellow(),
+            )),
+            CLIError::UnmockableInterface(interface_name, reason) => {
+                f.write_fmt(format_args!(
+                    "The interface class `{}` cannot be mocked because {}",
+                    interface_name.yellow(),
+                    reason,
+                ))?;
+                if !reason.is_fatal() {
+                    f.write_str("\n\thelp: use `--allow-empty` to generate an empty mock anyway")?;
+                }
+                Ok(())
+            }
+            CLIError::MethodNotFound(interface_name, method_name) => f.write_fmt(format_args!(
+                "The interface class `{}` has no pure virtual method named `{}`",
                 interface_name.yellow(),
+                method_name.yellow(),
+            )),
+            CLIError::ClassUnderTestNotFound(class_name) => f.write_fmt(format_args!(
+                "No class under test named `{}` was found in the specified translation unit",
+                class_name.yellow(),
+            )),
+            CLIError::ConcreteClassNotFound(class_name) => f.write_fmt(format_args!(
+                "No class named `{}` was found in the specified translation unit",
+                class_name.yellow(),
+            )),
+            CLIError::ConstructorNotFound(class_name) => f.write_fmt(format_args!(
+                "The class under test `{}` has no constructor through which dependencies could be injected",
+                class_name.yellow(),
+            )),
+            CLIError::OutputFileNotWritable(output_file, io_err) => f.write_fmt(format_args!(
+                "Failed to write to output file '{}': {}",
+                output_file.to_str().unwrap().yellow(),
+                io_err.to_string().italic(),
+            )),
+            CLIError::ArgumentCountMismatch(argument, count, interface_count) => {
+                f.write_fmt(format_args!(
+                    "Got {} `{}` arguments for {} interfaces",
+                    count.to_string().yellow(),
+                    argument.yellow(),
+                    interface_count.to_string().yellow(),
+                ))
+            }
+            CLIError::MockNameCollision(mock_name, interface_names) => f.write_fmt(format_args!(
+                "The mocks of {} would all be named `{}`\n\thelp: use `--mock` to name the mocks",
+                interface_names
+                    .iter()
+                    .map(|interface_name| format!("`{}`", interface_name.yellow()))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                mock_name.yellow(),
             )),
             CLIError::NotYetImplemented => f.write_str("Not yet implemented"),
         }
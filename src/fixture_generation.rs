@@ -0,0 +1,347 @@
+use clang::*;
+
+use crate::mock_generation::*;
+use crate::spy_generation::get_storable_type;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Ownership {
+    Reference,
+    RawPointer,
+    UniquePointer,
+    SharedPointer,
+}
+
+/// A constructor parameter of the class under test through which an interface is injected.
+pub struct Dependency<'tu> {
+    pub interface: Entity<'tu>,
+    pub ownership: Ownership,
+}
+
+/// Classifies a constructor parameter as a dependency if it is a reference, raw pointer, or smart
+/// pointer to an abstract class.
+pub fn get_dependency(parameter: Entity) -> Option<Dependency> {
+    let ty = parameter.get_type()?.get_canonical_type();
+    let (pointee, ownership) = match ty.get_kind() {
+        TypeKind::LValueReference | TypeKind::RValueReference => {
+            (ty.get_pointee_type()?, Ownership::Reference)
+        }
+        TypeKind::Pointer => (ty.get_pointee_type()?, Ownership::RawPointer),
+        TypeKind::Record => {
+            let declaration = ty.get_declaration()?;
+            if !get_qualified_name(declaration).starts_with("std::") {
+                return None;
+            }
+            let ownership = match declaration.get_name()?.as_str() {
+                "unique_ptr" => Ownership::UniquePointer,
+                "shared_ptr" => Ownership::SharedPointer,
+                _ => return None,
+            };
+            (
+                ty.get_template_argument_types()?.first().cloned()??,
+                ownership,
+            )
+        }
+        _ => return None,
+    };
+
+    pointee
+        .get_declaration()
+        .and_then(|decl| decl.get_definition())
+        .filter(|&interface| is_abstract_class(interface))
+        .map(|interface| Dependency {
+            interface,
+            ownership,
+        })
+}
+
+/// Picks the constructor of the class under test which injects the most dependencies, skipping
+/// copy and move constructors.
+pub fn find_injecting_constructor(class: Entity) -> Option<Entity> {
+    class
+        .get_children()
+        .into_iter()
+        .filter(|e| e.get_kind() == EntityKind::Constructor)
+        .filter(|e| !e.is_copy_constructor() && !e.is_move_constructor())
+        .fold(None, |best: Option<(Entity, usize)>, ctor| {
+            let count = ctor
+                .get_arguments()
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(get_dependency)
+                .count();
+            match best {
+                Some((_, best_count)) if best_count >= count => best,
+                _ => Some((ctor, count)),
+            }
+        })
+        .map(|(ctor, _)| ctor)
+}
+
+/// Whether the compiler declares a default constructor for the class, as it has no user-declared
+/// constructors at all.
+pub fn has_implicit_default_constructor(class: Entity) -> bool {
+    class
+        .get_children()
+        .iter()
+        .all(|e| e.get_kind() != EntityKind::Constructor)
+}
+
+/// Generates a GoogleTest fixture for the class under test, which injects `NiceMock`s of all its
+/// dependencies through the given constructor, or default-constructs it if there is none. The mock
+/// class names are looked up via the `mock_class_name` callback.
+pub fn generate_fixture_definition<F: Fn(Entity) -> String>(
+    class: Entity,
+    constructor: Option<Entity>,
+    fixture_name: &str,
+    mock_class_name: F,
+) -> String {
+    let class_name = get_qualified_name(class);
+    let mut members = Vec::new();
+    let mut arguments = Vec::new();
+
+    let parameter_names = constructor
+        .map(|ctor| get_parameter_names(&ctor))
+        .unwrap_or_default();
+    for (parameter, parameter_name) in constructor
+        .and_then(|ctor| ctor.get_arguments())
+        .unwrap_or_default()
+        .into_iter()
+        .zip(parameter_names)
+    {
+        match get_dependency(parameter) {
+            Some(dependency) => {
+                let mock_type = format!(
+                    "::testing::NiceMock<{}>",
+                    mock_class_name(dependency.interface)
+                );
+                let mock_name = format!("{}Mock", parameter_name);
+                let owner_name = format!("{}MockOwner", parameter_name);
+                match dependency.ownership {
+                    Ownership::Reference => {
+                        members.push(format!("{} {};", mock_type, mock_name));
+                        arguments.push(mock_name);
+                    }
+                    Ownership::RawPointer => {
+                        members.push(format!("{} {};", mock_type, mock_name));
+                        arguments.push(format!("&{}", mock_name));
+                    }
+                    Ownership::UniquePointer => {
+                        members.push(format!(
+                            "std::unique_ptr<{ty}> {owner} = std::make_unique<{ty}>();\n\t{ty}& {name} = *{owner};",
+                            ty = mock_type,
+                            owner = owner_name,
+                            name = mock_name
+                        ));
+                        arguments.push(format!("std::move({})", owner_name));
+                    }
+                    Ownership::SharedPointer => {
+                        members.push(format!(
+                            "std::shared_ptr<{ty}> {owner} = std::make_shared<{ty}>();\n\t{ty}& {name} = *{owner};",
+                            ty = mock_type,
+                            owner = owner_name,
+                            name = mock_name
+                        ));
+                        arguments.push(owner_name);
+                    }
+                }
+            }
+            None => arguments.push(format!(
+                "{} /* {} */",
+                format_placeholder_argument(parameter),
+                parameter_name
+            )),
+        }
+    }
+
+    let subject_name = get_subject_name(class);
+    members.push(format!("std::unique_ptr<{}> {};", class_name, subject_name));
+
+    format!(
+        "struct {} : ::testing::Test {{\n\tvoid SetUp() override {{\n\t\t{} = std::make_unique<{}>({});\n\t}}\n\nprotected:\n\t{}\n}};",
+        fixture_name,
+        subject_name,
+        class_name,
+        arguments.join(", "),
+        members.join("\n\t")
+    )
+}
+
+/// A value-initialized argument for a parameter which is not a dependency. Pointers are passed as
+/// `nullptr`, and types whose spelling is not a single (qualified) name, such as `unsigned int`,
+/// cannot be used in a functional cast and are initialized from `{}` instead.
+fn format_placeholder_argument(parameter: Entity) -> String {
+    let is_pointer = matches!(
+        parameter
+            .get_type()
+            .map(|ty| ty.get_canonical_type().get_kind()),
+        Some(TypeKind::Pointer | TypeKind::MemberPointer | TypeKind::Nullptr)
+    );
+    let ty = get_storable_type(&get_type_spelling(parameter).unwrap());
+    if is_pointer {
+        "nullptr".to_string()
+    } else if is_simple_type_name(&ty) {
+        format!("{}{{}}", ty)
+    } else {
+        "{}".to_string()
+    }
+}
+
+fn is_simple_type_name(ty: &str) -> bool {
+    let mut depth = 0;
+    ty.chars().all(|c| {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            _ => {}
+        }
+        depth > 0 || c == '>' || c.is_alphanumeric() || c == '_' || c == ':'
+    })
+}
+
+fn get_subject_name(class: Entity) -> String {
+    let name = class.get_name().unwrap();
+    let mut chars = name.chars();
+    chars
+        .next()
+        .map_or(String::new(), |c| c.to_lowercase().chain(chars).collect())
+}
+
+#[cfg(test)]
+mod dependency_tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    const PRELUDE: &str = r#"
+        namespace std {
+            template <typename T> struct default_delete {};
+            template <typename T, typename D = default_delete<T>> struct unique_ptr {};
+            template <typename T> struct shared_ptr {};
+        }
+        struct Storage {
+            virtual void save() = 0;
+        };
+        struct Concrete {
+            void save();
+        };
+    "#;
+
+    fn test_dependency_of_parameter(parameter: &str, expected: Option<Ownership>) {
+        test_class_from_source(
+            &format!(
+                "{} struct TestClass {{ TestClass({}); }};",
+                PRELUDE, parameter
+            ),
+            "TestClass",
+            |class| {
+                let ctor = find_injecting_constructor(class).unwrap();
+                let parameter = ctor.get_arguments().unwrap()[0];
+                assert_eq!(get_dependency(parameter).map(|dep| dep.ownership), expected);
+            },
+        )
+    }
+
+    #[test]
+    fn reference_to_interface_is_a_dependency() {
+        test_dependency_of_parameter("Storage& storage", Some(Ownership::Reference));
+    }
+
+    #[test]
+    fn raw_pointer_to_interface_is_a_dependency() {
+        test_dependency_of_parameter("Storage* storage", Some(Ownership::RawPointer));
+    }
+
+    #[test]
+    fn unique_pointer_to_interface_is_a_dependency() {
+        test_dependency_of_parameter(
+            "std::unique_ptr<Storage> storage",
+            Some(Ownership::UniquePointer),
+        );
+    }
+
+    #[test]
+    fn shared_pointer_to_interface_is_a_dependency() {
+        test_dependency_of_parameter(
+            "std::shared_ptr<Storage> storage",
+            Some(Ownership::SharedPointer),
+        );
+    }
+
+    #[test]
+    fn smart_pointer_outside_std_is_not_a_dependency() {
+        test_class_from_source(
+            &format!(
+                "{} template <typename T> struct unique_ptr {{}}; struct TestClass {{ TestClass(unique_ptr<Storage> storage); }};",
+                PRELUDE
+            ),
+            "TestClass",
+            |class| {
+                let ctor = find_injecting_constructor(class).unwrap();
+                let parameter = ctor.get_arguments().unwrap()[0];
+                assert!(get_dependency(parameter).is_none());
+            },
+        )
+    }
+
+    #[test]
+    fn reference_to_concrete_class_is_not_a_dependency() {
+        test_dependency_of_parameter("Concrete& concrete", None);
+    }
+
+    #[test]
+    fn value_parameter_is_not_a_dependency() {
+        test_dependency_of_parameter("int verbosity", None);
+    }
+
+    #[test]
+    fn fixture_injects_mocks_into_class_under_test() {
+        test_class_from_source(
+            &format!(
+                "{} struct Project {{ Project(int verbosity, const char* name, unsigned long size, std::unique_ptr<Storage> storage, Storage& backup); }};",
+                PRELUDE
+            ),
+            "Project",
+            |class| {
+                let ctor = find_injecting_constructor(class);
+                assert_eq_upto_whitespace(
+                    &generate_fixture_definition(class, ctor, "ProjectTest", |interface| {
+                        format!("{}Mock", interface.get_name().unwrap())
+                    }),
+                    r#"
+                        struct ProjectTest : ::testing::Test {
+                            void SetUp() override {
+                                project = std::make_unique<Project>(int{} /* verbosity */, nullptr /* name */, {} /* size */, std::move(storageMockOwner), backupMock);
+                            }
+
+                        protected:
+                            std::unique_ptr<::testing::NiceMock<StorageMock>> storageMockOwner = std::make_unique<::testing::NiceMock<StorageMock>>();
+                            ::testing::NiceMock<StorageMock>& storageMock = *storageMockOwner;
+                            ::testing::NiceMock<StorageMock> backupMock;
+                            std::unique_ptr<Project> project;
+                        };
+                    "#,
+                )
+            },
+        )
+    }
+
+    #[test]
+    fn fixture_default_constructs_class_without_constructors() {
+        test_class_from_source("struct Project { int verbosity; };", "Project", |class| {
+            assert!(find_injecting_constructor(class).is_none());
+            assert!(has_implicit_default_constructor(class));
+            assert_eq_upto_whitespace(
+                &generate_fixture_definition(class, None, "ProjectTest", |_| unreachable!()),
+                r#"
+                        struct ProjectTest : ::testing::Test {
+                            void SetUp() override {
+                                project = std::make_unique<Project>();
+                            }
+
+                        protected:
+                            std::unique_ptr<Project> project;
+                        };
+                    "#,
+            )
+        })
+    }
+}
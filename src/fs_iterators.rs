@@ -1,4 +1,8 @@
-use std::path::PathBuf;
+use crate::compile_commands::{matches_pattern, may_contain_build_directory};
+use std::path::{Path, PathBuf};
+
+/// Names of directories which are never descended into, in addition to hidden ones.
+const IGNORED_DIRECTORY_NAMES: &[&str] = &["node_modules"];
 
 pub trait Node: Sized + Clone + PartialEq + 'static {
     type Item;
@@ -110,18 +114,93 @@ impl Node for FilesystemDirectoryNode {
 
     fn children(&self) -> Box<dyn Iterator<Item = Self>> {
         match self.path.read_dir() {
-            Ok(contents) => Box::new(
-                contents
-                    .filter_map(Result::ok)
-                    .map(|entry| entry.path())
-                    .filter(|path| path.is_dir())
-                    .map(|path| FilesystemDirectoryNode { path }),
-            ),
+            Ok(contents) => {
+                let ignore_files = get_ignore_files(&self.path);
+                Box::new(
+                    contents
+                        .filter_map(Result::ok)
+                        .map(|entry| entry.path())
+                        .filter(|path| path.is_dir())
+                        .filter(move |path| !is_ignored(path, &ignore_files))
+                        .map(|path| FilesystemDirectoryNode { path }),
+                )
+            }
             Err(_) => Box::new(std::iter::empty()),
         }
     }
 }
 
+/// The contents of the `.gitignore` files which apply to the entries of a directory, alongside the
+/// directories containing them, from the root of the repository inwards.
+fn get_ignore_files(dir: &Path) -> Vec<(PathBuf, String)> {
+    let mut ignore_files = Vec::new();
+    for ancestor in dir.ancestors() {
+        if let Ok(contents) = std::fs::read_to_string(ancestor.join(".gitignore")) {
+            ignore_files.push((ancestor.to_path_buf(), contents));
+        }
+        if ancestor.join(".git").exists() {
+            break;
+        }
+    }
+    ignore_files.reverse();
+    ignore_files
+}
+
+/// Whether a directory is skipped while searching, i.e. whether it is hidden, one of
+/// [IGNORED_DIRECTORY_NAMES], or ignored by git. Build directories and their conventional parents
+/// (like `out`) are exempt from the latter since they are usually ignored, but are exactly where
+/// compile commands databases are found.
+fn is_ignored(dir: &Path, ignore_files: &[(PathBuf, String)]) -> bool {
+    let name = dir
+        .file_name()
+        .and_then(std::ffi::OsStr::to_str)
+        .unwrap_or_default();
+    if name.starts_with('.') || IGNORED_DIRECTORY_NAMES.contains(&name) {
+        return true;
+    }
+    if may_contain_build_directory(dir) {
+        return false;
+    }
+    ignore_files
+        .iter()
+        .rev()
+        .find_map(|(base, contents)| {
+            let relative_path = dir.strip_prefix(base).ok()?.to_str()?.replace('\\', "/");
+            get_gitignore_verdict(contents, &relative_path)
+        })
+        .unwrap_or(false)
+}
+
+/// Whether the directory at the given path (relative to the `.gitignore` file) is ignored according
+/// to the last matching pattern, or `None` if no pattern matches. Only the `*` wildcard is
+/// supported.
+fn get_gitignore_verdict(contents: &str, relative_path: &str) -> Option<bool> {
+    let name = relative_path.rsplit('/').next().unwrap_or(relative_path);
+    contents
+        .lines()
+        .rev()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .find_map(|line| {
+            let (ignored, pattern) = match line.strip_prefix('!') {
+                Some(pattern) => (false, pattern),
+                None => (true, line),
+            };
+            let pattern = pattern.trim_end_matches('/');
+            let pattern = pattern.strip_prefix("**/").unwrap_or(pattern);
+            let matches = if pattern.contains('/') {
+                matches_pattern(relative_path, pattern.trim_start_matches('/'))
+            } else {
+                matches_pattern(name, pattern)
+            };
+            if matches {
+                Some(ignored)
+            } else {
+                None
+            }
+        })
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -231,6 +310,49 @@ mod test {
         }
     }
 
+    #[test]
+    fn last_matching_gitignore_pattern_decides() {
+        let contents = "# comment\n/third_party/\nvendor*\n!vendor-patched\ndocs/api\n";
+        assert_eq!(get_gitignore_verdict(contents, "third_party"), Some(true));
+        assert_eq!(get_gitignore_verdict(contents, "src/third_party"), None);
+        assert_eq!(get_gitignore_verdict(contents, "src/vendored"), Some(true));
+        assert_eq!(
+            get_gitignore_verdict(contents, "vendor-patched"),
+            Some(false)
+        );
+        assert_eq!(get_gitignore_verdict(contents, "docs/api"), Some(true));
+        assert_eq!(get_gitignore_verdict(contents, "src"), None);
+    }
+
+    #[test]
+    fn hidden_and_ignored_directories_are_skipped() {
+        let root = std::env::temp_dir().join(format!("mockery-search-{}", std::process::id()));
+        for dir in [
+            ".git",
+            ".cache",
+            "node_modules",
+            "third_party",
+            "out/Debug",
+            "src",
+        ] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        std::fs::write(root.join(".gitignore"), "out/\nthird_party/\n").unwrap();
+
+        let children = |path: PathBuf| {
+            let mut names: Vec<_> = FilesystemDirectoryNode { path }
+                .children()
+                .map(|n| n.path.file_name().unwrap().to_str().unwrap().to_string())
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(children(root.clone()), vec!["out", "src"]);
+        assert_eq!(children(root.join("out")), vec!["Debug"]);
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
     #[test]
     fn content_of_root() {
         let root = TestNode {
@@ -0,0 +1,282 @@
+use clang::*;
+use itertools::Itertools;
+
+use crate::ast_iterators::IterableEntity;
+use crate::mock_generation::*;
+
+pub fn generate_implementation_declaration(interface_class: Entity, class_name: &str) -> String {
+    let mut declarations: Vec<_> = get_abstract_methods(interface_class)
+        .map(format_override_declaration)
+        .collect();
+    declarations.reverse();
+
+    wrap_in_namespaces(
+        interface_class,
+        format!(
+            "class {} : public {} {{\npublic:\n\t{}\n}};",
+            class_name,
+            get_qualified_name(interface_class),
+            declarations.join("\n\t")
+        ),
+    )
+}
+
+pub fn generate_implementation_definitions(interface_class: Entity, class_name: &str) -> String {
+    let mut definitions: Vec<_> = get_abstract_methods(interface_class)
+        .map(|method| format_out_of_line_definition(method, class_name))
+        .collect();
+    definitions.reverse();
+
+    wrap_in_namespaces(interface_class, definitions.join("\n\n"))
+}
+
+fn format_override_declaration(method: Entity) -> String {
+    format!("{} override;", format_method_signature(method, None))
+}
+
+/// Defines the method such that it throws when called. Since throwing from `noexcept` methods
+/// would terminate the program anyway, these terminate explicitly, which also keeps non-void
+/// methods from falling off the end of the function.
+fn format_out_of_line_definition(method: Entity, class_name: &str) -> String {
+    let message = format!(
+        "{}::{} is not implemented",
+        class_name,
+        method.get_name().unwrap()
+    );
+    let body = if get_method_exception_specification(&method).is_some() {
+        format!("// TODO: {}\n\tstd::terminate();", message)
+    } else {
+        format!("throw std::logic_error{{\"{}\"}};", message)
+    };
+    format!(
+        "{} {{\n\t{}\n}}",
+        format_method_signature(method, Some(class_name)),
+        body,
+    )
+}
+
+pub fn format_method_signature(method: Entity, class_name: Option<&str>) -> String {
+    let qualifiers = [
+        get_method_const_qualifier(&method),
+        get_method_ref_qualifier(&method),
+        get_method_exception_specification(&method),
+    ];
+
+    format!(
+        "{} {}{}({}){}",
+        method.get_result_type().unwrap().get_display_name(),
+        class_name.map_or(String::new(), |c| format!("{}::", c)),
+        method.get_name().unwrap(),
+        get_method_parameter_list(&method).join(", "),
+        qualifiers
+            .iter()
+            .flatten()
+            .map(|q| format!(" {}", q))
+            .collect::<String>()
+    )
+}
+
+fn get_method_parameter_list(method: &Entity) -> Vec<String> {
+    method
+        .get_arguments()
+        .unwrap()
+        .into_iter()
+        .map(
+            |arg| match (get_type_spelling(arg).unwrap(), arg.get_name()) {
+                (ty, Some(name)) => format!("{} {}", ty, name),
+                (ty, None) => ty,
+            },
+        )
+        .collect()
+}
+
+pub fn get_enclosing_namespaces(entity: Entity) -> Vec<String> {
+    let mut namespaces: Vec<_> = entity
+        .semantic_parents()
+        .skip(1)
+        .filter(|e| e.get_kind() == EntityKind::Namespace)
+        .filter_map(|e| e.get_name())
+        .collect();
+    namespaces.reverse();
+    namespaces
+}
+
+pub fn wrap_in_namespaces(entity: Entity, code: String) -> String {
+    let namespaces = get_enclosing_namespaces(entity);
+    if namespaces.is_empty() {
+        code
+    } else {
+        let namespace = namespaces.iter().join("::");
+        format!(
+            "namespace {} {{\n\n{}\n\n}} // namespace {}",
+            namespace, code, namespace
+        )
+    }
+}
+
+#[cfg(test)]
+mod implementation_method_tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    fn assert_override_for_function(func_decl: &str, override_decl: &str) {
+        test_method_for_function(func_decl, |method| {
+            assert_eq!(format_override_declaration(method), override_decl)
+        });
+    }
+
+    fn assert_definition_for_function(func_decl: &str, definition: &str) {
+        test_method_for_function(func_decl, |method| {
+            assert_eq_upto_whitespace(
+                &format_out_of_line_definition(method, "TestImpl"),
+                definition,
+            )
+        });
+    }
+
+    #[test]
+    fn override_for_simple_function() {
+        assert_override_for_function("virtual void foo() = 0;", "void foo() override;");
+    }
+
+    #[test]
+    fn override_for_function_with_named_parameters() {
+        assert_override_for_function(
+            "virtual int foo(const int &x, std::string y) = 0;",
+            "int foo(const int & x, std::string y) override;",
+        );
+    }
+
+    #[test]
+    fn override_for_function_with_unnamed_parameters() {
+        assert_override_for_function(
+            "virtual void foo(int, pair<int, double>) = 0;",
+            "void foo(int, pair<int, double>) override;",
+        );
+    }
+
+    #[test]
+    fn override_for_maximally_qualified_function() {
+        assert_override_for_function(
+            "virtual void foo() const&& noexcept = 0;",
+            "void foo() const && noexcept override;",
+        );
+    }
+
+    #[test]
+    fn override_for_lvalue_ref_qualified_function() {
+        assert_override_for_function("virtual void foo() & = 0;", "void foo() & override;");
+    }
+
+    #[test]
+    fn definition_is_qualified_with_class_name() {
+        assert_definition_for_function(
+            "virtual bool foo(int x) const = 0;",
+            r#"
+                bool TestImpl::foo(int x) const {
+                    throw std::logic_error{"TestImpl::foo is not implemented"};
+                }
+            "#,
+        );
+    }
+
+    #[test]
+    fn definition_of_potentially_throwing_function_throws() {
+        assert_definition_for_function(
+            "virtual bool foo() noexcept(false) = 0;",
+            r#"
+                bool TestImpl::foo() {
+                    throw std::logic_error{"TestImpl::foo is not implemented"};
+                }
+            "#,
+        );
+    }
+
+    #[test]
+    fn definition_of_noexcept_function_does_not_throw() {
+        assert_definition_for_function(
+            "virtual bool foo(int x) const noexcept = 0;",
+            r#"
+                bool TestImpl::foo(int x) const noexcept {
+                    // TODO: TestImpl::foo is not implemented
+                    std::terminate();
+                }
+            "#,
+        );
+    }
+}
+
+#[cfg(test)]
+mod generate_implementation_from_interface {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn implementation_declares_overrides_of_pure_virtual_functions() {
+        test_class_from_source(
+            r#"
+                struct Foo {
+                    virtual void foo(int x) = 0;
+                    virtual void bar() const = 0;
+                    virtual void baz();
+                };
+            "#,
+            "Foo",
+            |class| {
+                assert_eq_upto_whitespace(
+                    &generate_implementation_declaration(class, "FooImpl"),
+                    r#"
+                        class FooImpl : public Foo {
+                        public:
+                            void foo(int x) override;
+                            void bar() const override;
+                        };
+                    "#,
+                )
+            },
+        )
+    }
+
+    #[test]
+    fn implementation_is_placed_in_namespace_of_interface() {
+        test_class_from_source(
+            r#"
+                namespace Bar {
+                    namespace Baz {
+                        struct Foo {
+                            virtual void foo() = 0;
+                        };
+                    }
+                }
+            "#,
+            "Foo",
+            |class| {
+                assert_eq_upto_whitespace(
+                    &generate_implementation_declaration(class, "FooImpl"),
+                    r#"
+                        namespace Bar::Baz {
+
+                        class FooImpl : public Bar::Baz::Foo {
+                        public:
+                            void foo() override;
+                        };
+
+                        } // namespace Bar::Baz
+                    "#,
+                );
+                assert_eq_upto_whitespace(
+                    &generate_implementation_definitions(class, "FooImpl"),
+                    r#"
+                        namespace Bar::Baz {
+
+                        void FooImpl::foo() {
+                            throw std::logic_error{"FooImpl::foo is not implemented"};
+                        }
+
+                        } // namespace Bar::Baz
+                    "#,
+                );
+            },
+        )
+    }
+}
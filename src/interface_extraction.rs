@@ -0,0 +1,279 @@
+use clang::source::SourceLocation;
+use clang::token::{Token, TokenKind};
+use clang::*;
+
+use crate::implementation_generation::*;
+use crate::mock_generation::is_deleted_method;
+use crate::patch::Insertion;
+
+/// The public, non-static member functions of a class, which make up its extracted interface.
+/// Deleted and defaulted methods as well as assignment operators are left out, as they are not
+/// part of the behavior which an interface abstracts.
+pub fn get_extractable_methods(class: Entity) -> Vec<Entity> {
+    class
+        .get_children()
+        .into_iter()
+        .filter(|e| e.get_kind() == EntityKind::Method)
+        .filter(|e| e.get_accessibility() == Some(Accessibility::Public))
+        .filter(|e| !e.is_static_method())
+        .filter(|e| !e.is_defaulted() && !is_deleted_method(*e))
+        .filter(|e| e.get_name().as_deref() != Some("operator="))
+        .collect()
+}
+
+pub fn generate_interface_definition(class: Entity, interface_name: &str) -> String {
+    let declarations: Vec<_> = get_extractable_methods(class)
+        .into_iter()
+        .map(|method| format!("virtual {} = 0;", format_method_signature(method, None)))
+        .collect();
+
+    wrap_in_namespaces(
+        class,
+        format!(
+            "struct {} {{\n\tvirtual ~{}() = default;\n\n\t{}\n}};",
+            interface_name,
+            interface_name,
+            declarations.join("\n\t")
+        ),
+    )
+}
+
+/// Determines the insertions into the source file of the class which are needed to make it derive
+/// from the extracted interface and mark the extracted methods as `override`.
+pub fn get_derivation_insertions(class: Entity, interface_name: &str) -> Vec<Insertion> {
+    let last_base = class
+        .get_children()
+        .into_iter()
+        .rfind(|e| e.get_kind() == EntityKind::BaseSpecifier);
+    let base_insertion = match last_base {
+        Some(base) => base
+            .get_range()
+            .map(|r| insertion_at(r.get_end(), format!(", public {}", interface_name))),
+        None => class
+            .get_range()
+            .map(|r| r.tokenize())
+            .unwrap_or_default()
+            .into_iter()
+            .find(|t| t.get_kind() == TokenKind::Punctuation && t.get_spelling() == "{")
+            .map(|t| {
+                insertion_at(
+                    t.get_range().get_start(),
+                    format!(": public {} ", interface_name),
+                )
+            }),
+    };
+
+    base_insertion
+        .into_iter()
+        .chain(
+            get_extractable_methods(class)
+                .into_iter()
+                .filter_map(get_override_insertion),
+        )
+        .collect()
+}
+
+/// Determines the insertion of an `#include` of the header with the extracted interface into the
+/// source file of the class: after the last existing `#include`, or else after `#pragma once` or
+/// at the top of the file. The line ending of the surrounding lines is retained.
+pub fn get_include_insertion(source: &str, header: &str) -> Insertion {
+    let lines: Vec<_> = source.split('\n').collect();
+    let directive = |line: &str, name: &str| {
+        matches!(
+            line.trim_start().strip_prefix('#'),
+            Some(rest) if rest.trim_start().starts_with(name)
+        )
+    };
+    let anchor = lines
+        .iter()
+        .rposition(|line| directive(line, "include"))
+        .or_else(|| lines.iter().position(|line| directive(line, "pragma once")));
+    let line_ending = match anchor {
+        Some(i) if lines[i].ends_with('\r') => "\r\n",
+        _ => "\n",
+    };
+    let include = format!("#include \"{}\"", header);
+
+    match anchor {
+        Some(i) if i + 1 < lines.len() => Insertion {
+            line: i as u32 + 2,
+            column: 1,
+            text: format!("{}{}", include, line_ending),
+        },
+        Some(i) => Insertion {
+            line: i as u32 + 1,
+            column: lines[i].len() as u32 + 1,
+            text: format!("{}{}", line_ending, include),
+        },
+        None => Insertion {
+            line: 1,
+            column: 1,
+            text: format!("{}{}{}", include, line_ending, line_ending),
+        },
+    }
+}
+
+fn get_override_insertion(method: Entity) -> Option<Insertion> {
+    let range = method.get_range()?;
+    let tokens = range.tokenize();
+    let name = method.get_name()?;
+
+    let name_index = tokens.iter().position(|t| t.get_spelling() == name)?;
+    let mut depth = 0;
+    let mut parameters_closed = false;
+    for token in &tokens[name_index + 1..] {
+        match (token.get_kind(), token.get_spelling().as_str()) {
+            (TokenKind::Punctuation, "(") => depth += 1,
+            (TokenKind::Punctuation, ")") => {
+                depth -= 1;
+                parameters_closed |= depth == 0;
+            }
+            (TokenKind::Identifier, "override") | (TokenKind::Identifier, "final")
+                if depth == 0 =>
+            {
+                return None
+            }
+            (TokenKind::Punctuation, "{") | (TokenKind::Punctuation, "=")
+                if depth == 0 && parameters_closed =>
+            {
+                return Some(insertion_before(token, "override "));
+            }
+            _ => {}
+        }
+    }
+
+    Some(insertion_at(range.get_end(), " override".to_string()))
+}
+
+fn insertion_before(token: &Token, text: &str) -> Insertion {
+    insertion_at(token.get_range().get_start(), text.to_string())
+}
+
+fn insertion_at(location: SourceLocation, text: String) -> Insertion {
+    let loc = location.get_file_location();
+    Insertion {
+        line: loc.line,
+        column: loc.column,
+        text,
+    }
+}
+
+#[cfg(test)]
+mod interface_extraction_tests {
+    use super::*;
+    use crate::patch::apply_insertions;
+    use crate::test_utils::*;
+
+    const SOURCE: &str = r#"
+        namespace Bar {
+            class Foo {
+            public:
+                Foo(int x);
+                Foo(const Foo&) = delete;
+                Foo& operator=(const Foo&) = delete;
+                Foo& operator=(Foo&&) = default;
+                static Foo create();
+                [[nodiscard]] int get() const noexcept;
+                void set(int value) & { }
+                auto name() const -> const char*;
+
+            private:
+                void helper();
+            };
+        }
+    "#;
+
+    #[test]
+    fn interface_contains_public_non_static_methods() {
+        test_class_from_source(SOURCE, "Foo", |class| {
+            assert_eq_upto_whitespace(
+                &generate_interface_definition(class, "IFoo"),
+                r#"
+                    namespace Bar {
+
+                    struct IFoo {
+                        virtual ~IFoo() = default;
+
+                        virtual int get() const noexcept = 0;
+                        virtual void set(int value) & = 0;
+                        virtual const char * name() const = 0;
+                    };
+
+                    } // namespace Bar
+                "#,
+            )
+        })
+    }
+
+    #[test]
+    fn class_is_patched_to_derive_from_interface() {
+        test_class_from_source(SOURCE, "Foo", |class| {
+            assert_eq!(
+                apply_insertions(SOURCE, &get_derivation_insertions(class, "IFoo")),
+                r#"
+        namespace Bar {
+            class Foo : public IFoo {
+            public:
+                Foo(int x);
+                Foo(const Foo&) = delete;
+                Foo& operator=(const Foo&) = delete;
+                Foo& operator=(Foo&&) = default;
+                static Foo create();
+                [[nodiscard]] int get() const noexcept override;
+                void set(int value) & override { }
+                auto name() const -> const char* override;
+
+            private:
+                void helper();
+            };
+        }
+    "#
+            )
+        })
+    }
+
+    #[test]
+    fn include_is_inserted_after_last_include() {
+        let source =
+            "#pragma once\r\n#include <string>\r\n#include \"Bar.h\"\r\n\r\nclass Foo {};\r\n";
+        assert_eq!(
+            apply_insertions(source, &[get_include_insertion(source, "IFoo.h")]),
+            "#pragma once\r\n#include <string>\r\n#include \"Bar.h\"\r\n#include \"IFoo.h\"\r\n\r\nclass Foo {};\r\n"
+        );
+    }
+
+    #[test]
+    fn include_is_inserted_after_pragma_once_or_at_top() {
+        let source = "#pragma once\n\nclass Foo {};";
+        assert_eq!(
+            apply_insertions(source, &[get_include_insertion(source, "IFoo.h")]),
+            "#pragma once\n#include \"IFoo.h\"\n\nclass Foo {};"
+        );
+        let source = "class Foo {};";
+        assert_eq!(
+            apply_insertions(source, &[get_include_insertion(source, "IFoo.h")]),
+            "#include \"IFoo.h\"\n\nclass Foo {};"
+        );
+    }
+
+    #[test]
+    fn existing_base_classes_are_retained() {
+        let source = r#"
+            struct Base {};
+            struct Foo : Base {
+                virtual void foo() override;
+            };
+        "#;
+        test_class_from_source(source, "Foo", |class| {
+            assert_eq!(
+                apply_insertions(source, &get_derivation_insertions(class, "IFoo")),
+                r#"
+            struct Base {};
+            struct Foo : Base, public IFoo {
+                virtual void foo() override;
+            };
+        "#
+            )
+        })
+    }
+}
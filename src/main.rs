@@ -1,9 +1,19 @@
 mod app;
 mod ast_iterators;
 mod cli;
+mod compile_commands;
+mod diagnostics;
 mod error;
+mod fixture_generation;
 mod fs_iterators;
+mod implementation_generation;
+mod interface_extraction;
 mod mock_generation;
+mod patch;
+mod printer_generation;
+mod spy_generation;
+mod suggestions;
+mod system_includes;
 
 #[cfg(test)]
 mod test_utils;
@@ -36,6 +46,10 @@ fn cli_main() -> CLIResult<()> {
     match opts.subcmd {
         SubCommand::Create(crt) => app.run_create(crt),
         SubCommand::Update(upd) => app.run_update(upd),
+        SubCommand::Implement(imp) => app.run_implement(imp),
+        SubCommand::Fixture(fix) => app.run_fixture(fix),
+        SubCommand::Expect(exp) => app.run_expect(exp),
+        SubCommand::ExtractInterface(ext) => app.run_extract_interface(ext),
         SubCommand::Dump(dmp) => app.run_dump(dmp),
     }
 }
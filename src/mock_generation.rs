@@ -1,3 +1,4 @@
+use clang::source::SourceRange;
 use clang::token::TokenKind;
 use clang::*;
 use itertools::Itertools;
@@ -5,34 +6,91 @@ use std::convert::identity;
 use std::iter::once;
 
 use crate::ast_iterators::IterableEntity;
+use crate::spy_generation::get_storable_type;
 
 pub fn find_class_entity<'tu>(tu: &'tu TranslationUnit, class_name: &str) -> Option<Entity<'tu>> {
-    find_class_entity_impl(tu.get_entity().get_children(), class_name)
+    find_class_entities(tu, class_name).into_iter().next()
 }
 
-fn find_class_entity_impl<'tu>(
-    entities: Vec<Entity<'tu>>,
-    class_name: &str,
-) -> Option<Entity<'tu>> {
-    if entities.is_empty() {
-        None
-    } else {
-        entities
-            .iter()
-            .cloned()
-            .find(|&e| e.get_name() == Some(class_name.to_string()))
-            .filter(is_class_entity)
-            .or_else(|| {
-                find_class_entity_impl(
-                    entities
-                        .into_iter()
-                        .map(|e| e.get_children())
-                        .flatten()
-                        .collect(),
-                    class_name,
-                )
-            })
+/// Finds all classes matching a possibly qualified name, such as `Storage`, `net::Storage`, or
+/// `::Storage`. The latter is anchored at the global namespace, whereas the former may match
+/// classes in any enclosing scope. Inline and anonymous namespaces may be omitted from the
+/// qualification. Multiple declarations of the same class are only reported once, resolving to the
+/// class definition where it is visible.
+pub fn find_class_entities<'tu>(tu: &'tu TranslationUnit, class_name: &str) -> Vec<Entity<'tu>> {
+    let (anchored, class_name) = match class_name.strip_prefix("::") {
+        Some(class_name) => (true, class_name),
+        None => (false, class_name),
+    };
+    let components: Vec<_> = class_name.split("::").map(str::trim).collect();
+
+    let mut classes: Vec<Entity> = Vec::new();
+    tu.get_entity().visit_children(|entity, _| {
+        if is_class_entity(&entity)
+            && matches_qualified_name(entity, &components, anchored)
+            && !classes
+                .iter()
+                .any(|class| class.get_canonical_entity() == entity.get_canonical_entity())
+        {
+            classes.push(entity.get_definition().unwrap_or(entity));
+        }
+        EntityVisitResult::Recurse
+    });
+    classes
+}
+
+fn matches_qualified_name(entity: Entity, components: &[&str], anchored: bool) -> bool {
+    let mut components = components.iter().rev().peekable();
+    for scope in entity.semantic_parents() {
+        match components.peek() {
+            Some(&&name) if scope.get_name().as_deref() == Some(name) => {
+                components.next();
+            }
+            _ if is_transparent_namespace(scope) => {}
+            Some(_) => return false,
+            None => return !anchored,
+        }
     }
+    components.peek().is_none()
+}
+
+/// Whether the entity is an inline or anonymous namespace, whose members can be referred to
+/// without naming the namespace.
+fn is_transparent_namespace(entity: Entity) -> bool {
+    entity.get_kind() == EntityKind::Namespace
+        && (entity.get_name().is_none()
+            || match (entity.get_range(), entity.get_location()) {
+                (Some(range), Some(name_location)) => {
+                    SourceRange::new(range.get_start(), name_location)
+                        .tokenize()
+                        .first()
+                        .map(|token| token.get_spelling())
+                        .as_deref()
+                        == Some("inline")
+                }
+                _ => false,
+            })
+}
+
+/// Finds all class definitions in the translation unit, outside of system headers, which have at
+/// least one unimplemented pure virtual member function.
+pub fn find_abstract_classes<'tu>(tu: &'tu TranslationUnit) -> Vec<Entity<'tu>> {
+    let mut classes = Vec::new();
+    tu.get_entity().visit_children(|entity, _| {
+        if entity.is_in_system_header() {
+            EntityVisitResult::Continue
+        } else if is_class_entity(&entity) {
+            if entity.is_definition() && is_abstract_class(entity) && !classes.contains(&entity) {
+                classes.push(entity);
+            }
+            EntityVisitResult::Recurse
+        } else if entity.get_kind() == EntityKind::Namespace {
+            EntityVisitResult::Recurse
+        } else {
+            EntityVisitResult::Continue
+        }
+    });
+    classes
 }
 
 fn is_class_entity(entity: &Entity) -> bool {
@@ -53,7 +111,7 @@ fn get_direct_base_classes(class: Entity) -> impl DoubleEndedIterator<Item = Ent
         })
 }
 
-fn get_all_base_classes(class: Entity) -> impl DoubleEndedIterator<Item = Entity> {
+pub fn get_all_base_classes(class: Entity) -> impl DoubleEndedIterator<Item = Entity> {
     get_direct_base_classes(class).flat_map(|base| {
         get_all_base_classes(base.clone())
             .chain(once(base))
@@ -62,7 +120,7 @@ fn get_all_base_classes(class: Entity) -> impl DoubleEndedIterator<Item = Entity
     })
 }
 
-fn get_abstract_methods(class: Entity) -> impl Iterator<Item = Entity> {
+pub fn get_abstract_methods(class: Entity) -> impl Iterator<Item = Entity> {
     get_all_base_classes(class)
         .chain(once(class))
         .flat_map(|e| e.get_children().into_iter())
@@ -82,20 +140,532 @@ fn get_abstract_methods(class: Entity) -> impl Iterator<Item = Entity> {
         .filter_map(identity)
 }
 
-pub fn generate_mock_definition(interface_class: Entity, mock_class_name: &str) -> String {
-    let mut mock_methods: Vec<_> = get_abstract_methods(interface_class)
-        .map(format_mock_method_definition)
+#[derive(Default)]
+pub struct MockOptions {
+    /// Default return values keyed by the spelling of the return type. A constructor is emitted
+    /// which installs them as `ON_CALL` default actions of the mocked methods returning these types.
+    /// gMock's own defaults (`false`, `0`, `nullptr`, value-initialized objects) are not repeated.
+    pub default_returns: Vec<(String, String)>,
+
+    /// Name of a fake class which the mock holds an instance of. When set, a `DelegateToFake()`
+    /// member is emitted which delegates all mocked methods to the fake by default.
+    pub delegate_to: Option<String>,
+
+    /// Capture the last callback passed to any `std::function` parameter of a mocked method, and
+    /// emit a companion `MockFunction` member and an `Invoke...Callback` helper for each of them.
+    /// The helper reports each invocation to the companion before invoking the captured callback.
+    pub mock_callbacks: bool,
+
+    /// Emit a `NiceMock` alias for the mock class.
+    pub nice_alias: bool,
+
+    /// Emit a `StrictMock` alias for the mock class.
+    pub strict_alias: bool,
+}
+
+pub fn is_abstract_class(class: Entity) -> bool {
+    get_abstract_methods(class).next().is_some()
+}
+
+/// Reasons for which a class cannot be mocked, or for which its mock would be empty.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UnmockableReason {
+    Final,
+    NoVirtualMethods,
+    NonVirtualDestructor,
+    AllMethodsImplemented,
+}
+
+impl UnmockableReason {
+    /// Whether the class cannot be mocked at all, as opposed to merely yielding an empty mock.
+    pub fn is_fatal(self) -> bool {
+        matches!(
+            self,
+            UnmockableReason::Final | UnmockableReason::NonVirtualDestructor
+        )
+    }
+}
+
+impl std::fmt::Display for UnmockableReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            UnmockableReason::Final => "it is declared `final`",
+            UnmockableReason::NoVirtualMethods => "it has no virtual member functions",
+            UnmockableReason::NonVirtualDestructor => "its destructor is public but not virtual",
+            UnmockableReason::AllMethodsImplemented => {
+                "all of its virtual member functions are implemented, either by itself or by its base classes"
+            }
+        })
+    }
+}
+
+pub fn get_unmockable_reason(class: Entity) -> Option<UnmockableReason> {
+    let members: Vec<_> = get_all_base_classes(class)
+        .chain(once(class))
+        .flat_map(|e| e.get_children().into_iter())
         .collect();
-    mock_methods.reverse();
+    let is_virtual = |kind| {
+        members
+            .iter()
+            .any(|e| e.get_kind() == kind && e.is_virtual_method())
+    };
+    let has_public_destructor = !class.get_children().iter().any(|e| {
+        e.get_kind() == EntityKind::Destructor
+            && e.get_accessibility() != Some(Accessibility::Public)
+    });
+
+    if class
+        .get_children()
+        .iter()
+        .any(|e| e.get_kind() == EntityKind::FinalAttr)
+    {
+        Some(UnmockableReason::Final)
+    } else if has_public_destructor && !is_virtual(EntityKind::Destructor) {
+        // Checked before the reasons for an empty mock, which `--allow-empty` would let through.
+        Some(UnmockableReason::NonVirtualDestructor)
+    } else if !is_virtual(EntityKind::Method) {
+        Some(UnmockableReason::NoVirtualMethods)
+    } else if !is_abstract_class(class) {
+        Some(UnmockableReason::AllMethodsImplemented)
+    } else {
+        None
+    }
+}
+
+pub fn generate_mock_definition(
+    interface_class: Entity,
+    mock_class_name: &str,
+    options: &MockOptions,
+) -> String {
+    let mut methods: Vec<_> = get_abstract_methods(interface_class).collect();
+    methods.reverse();
+
+    let mut members = Vec::new();
+    if !options.default_returns.is_empty() || options.mock_callbacks {
+        let default_actions: Vec<_> = methods
+            .iter()
+            .filter_map(|&method| {
+                let overloaded = is_overloaded(method, &methods);
+                if options.mock_callbacks && !get_callback_parameters(&method).is_empty() {
+                    Some(format_callback_capturing_action(
+                        method, &methods, overloaded, options,
+                    ))
+                } else {
+                    format_default_action(method, overloaded, options)
+                }
+            })
+            .collect();
+        if !default_actions.is_empty() {
+            members.push(format!(
+                "{}() {{\n\t\t{}\n\t}}\n",
+                mock_class_name,
+                default_actions.join("\n\t\t")
+            ));
+        }
+    }
+    if options.delegate_to.is_some() {
+        let delegating_actions: Vec<_> = methods
+            .iter()
+            .map(|&method| format_delegating_action(method, is_overloaded(method, &methods)))
+            .collect();
+        members.push(format!(
+            "void DelegateToFake() {{\n\t\t{}\n\t}}\n",
+            delegating_actions.join("\n\t\t")
+        ));
+    }
+    members.extend(methods.iter().cloned().map(format_mock_method_definition));
+
+    let callback_members = if options.mock_callbacks {
+        methods
+            .iter()
+            .flat_map(|method| format_callback_members(method, &methods))
+            .collect::<String>()
+    } else {
+        String::new()
+    };
+
+    let private_members = options
+        .delegate_to
+        .as_ref()
+        .map_or(String::new(), |fake_class_name| {
+            format!("\nprivate:\n\t{} fake_;\n", fake_class_name)
+        });
+
+    let mut aliases = Vec::new();
+    if options.nice_alias {
+        aliases.push(format_mock_alias("Nice", mock_class_name));
+    }
+    if options.strict_alias {
+        aliases.push(format_mock_alias("Strict", mock_class_name));
+    }
 
     format!(
-        "struct {} : {} {{\n\t{}\n}};",
+        "struct {} : {} {{\n\t{}\n{}{}}};{}",
         mock_class_name,
         get_qualified_name(interface_class),
-        mock_methods.join("\n\t")
+        members.join("\n\t"),
+        callback_members,
+        private_members,
+        aliases
+            .iter()
+            .map(|alias| format!("\n\n{}", alias))
+            .collect::<String>()
+    )
+}
+
+pub fn generate_expect_call_snippets(
+    interface_class: Entity,
+    mock_variable: &str,
+    method_name: Option<&str>,
+) -> Vec<String> {
+    let mut methods: Vec<_> = get_abstract_methods(interface_class).collect();
+    methods.reverse();
+    methods
+        .iter()
+        .filter(|method| method_name.is_none() || method.get_name().as_deref() == method_name)
+        .map(|&method| {
+            format_expect_call_snippet(method, mock_variable, is_overloaded(method, &methods))
+        })
+        .collect()
+}
+
+fn format_expect_call_snippet(method: Entity, mock_variable: &str, overloaded: bool) -> String {
+    let result_type = method.get_result_type().unwrap();
+    let action = match result_type.get_kind() {
+        TypeKind::Void | TypeKind::RValueReference => None,
+        TypeKind::LValueReference => Some(format!(
+            "::testing::ReturnRef(/* {} */)",
+            get_storable_type(&result_type.get_display_name())
+        )),
+        _ => {
+            let value = get_default_return_value(result_type)
+                .unwrap_or(format!("{}{{}}", result_type.get_display_name()));
+            Some(if is_move_only_type(result_type) {
+                format!("::testing::Return(::testing::ByMove({}))", value)
+            } else {
+                format!("::testing::Return({})", value)
+            })
+        }
+    };
+
+    // Neither `Return` nor `ReturnRef` can produce an rvalue reference, so the action is left to
+    // the user.
+    let note = if result_type.get_kind() == TypeKind::RValueReference {
+        format!(
+            " // TODO: add an action returning {}",
+            result_type.get_display_name()
+        )
+    } else {
+        String::new()
+    };
+
+    format!(
+        "EXPECT_CALL({}, {}({})){};{}",
+        format_object_expression(&method, mock_variable, overloaded),
+        method.get_name().unwrap(),
+        format_wildcard_matchers(&method, overloaded),
+        action.map_or(String::new(), |a| format!(".WillOnce({})", a)),
+        note
+    )
+}
+
+fn format_mock_alias(kind: &str, mock_class_name: &str) -> String {
+    format!(
+        "using {}{} = ::testing::{}Mock<{}>;",
+        kind, mock_class_name, kind, mock_class_name
+    )
+}
+
+/// Names a method for use in the names of members generated for it, numbering its overloads after
+/// the first one (`foo`, `foo2`, ...) while skipping the names of other methods.
+pub fn get_overload_name(method: &Entity, methods: &[Entity]) -> String {
+    let name = method.get_name().unwrap();
+    let overload = methods
+        .iter()
+        .filter(|m| m.get_name() == method.get_name())
+        .position(|m| m == method)
+        .unwrap_or(0);
+    if overload == 0 {
+        return name;
+    }
+    (2..)
+        .map(|number| format!("{}{}", name, number))
+        .filter(|candidate| {
+            methods
+                .iter()
+                .all(|m| m.get_name().as_ref() != Some(candidate))
+        })
+        .nth(overload - 1)
+        .unwrap()
+}
+
+/// The names of the function's parameters, naming unnamed ones `arg<index>` unless that is the name
+/// of another parameter.
+pub fn get_parameter_names(function: &Entity) -> Vec<String> {
+    let names: Vec<_> = function
+        .get_arguments()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|arg| arg.get_name())
+        .collect();
+    names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            name.clone().unwrap_or_else(|| {
+                let mut name = format!("arg{}", i);
+                while names.contains(&Some(name.clone())) {
+                    name.push('_');
+                }
+                name
+            })
+        })
+        .collect()
+}
+
+fn is_overloaded(method: Entity, methods: &[Entity]) -> bool {
+    methods
+        .iter()
+        .filter(|m| m.get_name() == method.get_name())
+        .count()
+        > 1
+}
+
+fn format_default_action(
+    method: Entity,
+    overloaded: bool,
+    options: &MockOptions,
+) -> Option<String> {
+    let value =
+        get_configured_return_value(method.get_result_type().unwrap(), &options.default_returns)?;
+
+    Some(format!(
+        "ON_CALL({}, {}({})).WillByDefault(::testing::Return({}));",
+        format_object_expression(&method, "*this", overloaded),
+        method.get_name().unwrap(),
+        format_wildcard_matchers(&method, overloaded),
+        value
+    ))
+}
+
+fn format_delegating_action(method: Entity, overloaded: bool) -> String {
+    format!(
+        "ON_CALL({}, {}({})).WillByDefault([this](auto&&... args) -> decltype(auto) {{\n\t\t\treturn {}.{}(std::forward<decltype(args)>(args)...);\n\t\t}});",
+        format_object_expression(&method, "*this", overloaded),
+        method.get_name().unwrap(),
+        format_wildcard_matchers(&method, overloaded),
+        format_object_expression(&method, "fake_", overloaded),
+        method.get_name().unwrap(),
     )
 }
 
+fn format_wildcard_matchers(method: &Entity, overloaded: bool) -> String {
+    get_method_parameter_type_list(method)
+        .into_iter()
+        .map(|ty| {
+            if overloaded {
+                format!("::testing::A<{}>()", ty)
+            } else {
+                "::testing::_".to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Spells out an expression referring to `object` with the cv- and ref-qualification that is
+/// required to select the given method. Const-qualification is only made explicit when the method
+/// is overloaded, as that is the only case where it matters.
+fn format_object_expression(method: &Entity, object: &str, overloaded: bool) -> String {
+    let object = if overloaded && method.is_const_method() {
+        format!("std::as_const({})", object)
+    } else {
+        object.to_string()
+    };
+    match method.get_type().unwrap().get_ref_qualifier() {
+        Some(RefQualifier::RValue) => format!("std::move({})", object),
+        _ => object,
+    }
+}
+
+/// Captures the callbacks passed to the method. Since `DoDefault()` cannot be part of a composite
+/// action, return types without a configured default value are returned through gMock's
+/// `DefaultValue`, which is what the default action would return as well.
+fn format_callback_capturing_action(
+    method: Entity,
+    methods: &[Entity],
+    overloaded: bool,
+    options: &MockOptions,
+) -> String {
+    let mut actions: Vec<_> = get_callback_parameters(&method)
+        .into_iter()
+        .map(|(index, _)| {
+            format!(
+                "::testing::SaveArg<{}>(&{})",
+                index,
+                get_callback_member_name(&method, methods, index)
+            )
+        })
+        .collect();
+    let result_type = method.get_result_type().unwrap();
+    if result_type.get_kind() != TypeKind::Void {
+        actions.push(
+            match get_configured_return_value(result_type, &options.default_returns) {
+                Some(value) => format!("::testing::Return({})", value),
+                None => {
+                    let spelling = result_type.get_display_name();
+                    format!(
+                        "::testing::InvokeWithoutArgs([]() -> {} {{ return ::testing::DefaultValue<{}>::Get(); }})",
+                        spelling, spelling
+                    )
+                }
+            },
+        );
+    }
+    let action = if actions.len() == 1 {
+        actions.remove(0)
+    } else {
+        format!("::testing::DoAll({})", actions.join(", "))
+    };
+
+    format!(
+        "ON_CALL({}, {}({})).WillByDefault({});",
+        format_object_expression(&method, "*this", overloaded),
+        method.get_name().unwrap(),
+        format_wildcard_matchers(&method, overloaded),
+        action
+    )
+}
+
+/// Declares the member capturing a callback, its companion `MockFunction`, through which tests can
+/// set expectations on the invocations of the callback, and a helper invoking both.
+fn format_callback_members(method: &Entity, methods: &[Entity]) -> Vec<String> {
+    get_callback_parameters(method)
+        .into_iter()
+        .map(|(index, signature)| {
+            let member_name = get_callback_member_name(method, methods, index);
+            format!(
+                "\n\t::testing::MockFunction<{sig}> {name}Mock;\n\tstd::function<{sig}> {name};\n\ttemplate <typename... Args>\n\tdecltype(auto) Invoke{helper}(Args&&... args) {{\n\t\t{name}Mock.Call(args...);\n\t\treturn {name}(std::forward<Args>(args)...);\n\t}}\n",
+                sig = signature,
+                name = member_name,
+                helper = capitalize(&member_name),
+            )
+        })
+        .collect()
+}
+
+/// Finds the parameters of the method which take a `std::function`, yielding their indices along
+/// with the signature of the function.
+fn get_callback_parameters(method: &Entity) -> Vec<(usize, String)> {
+    get_method_parameter_type_list(method)
+        .iter()
+        .map(|ty| get_storable_type(ty))
+        .enumerate()
+        .filter_map(|(index, ty)| {
+            ty.strip_prefix("std::function<")
+                .and_then(|sig| sig.strip_suffix('>'))
+                .map(|sig| (index, sig.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Names the member capturing a callback after the method, numbering the callback parameters of
+/// methods taking several of them.
+fn get_callback_member_name(method: &Entity, methods: &[Entity], index: usize) -> String {
+    let name = get_overload_name(method, methods);
+    if get_callback_parameters(method).len() > 1 {
+        format!("{}Callback{}", name, index)
+    } else {
+        format!("{}Callback", name)
+    }
+}
+
+fn capitalize(name: &str) -> String {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .map_or(String::new(), |c| c.to_uppercase().chain(chars).collect())
+}
+
+/// The default return value given by the user for the type, if any.
+fn get_configured_return_value(ty: Type, default_returns: &[(String, String)]) -> Option<String> {
+    let spelling = ty.get_display_name();
+    default_returns
+        .iter()
+        .find(|(t, _)| *t == spelling)
+        .map(|(_, value)| value.clone())
+}
+
+/// The value which gMock returns by default for the type, if it is known to have a simple spelling.
+fn get_default_return_value(ty: Type) -> Option<String> {
+    use TypeKind::*;
+    match ty.get_canonical_type().get_kind() {
+        Bool => Some("false"),
+        CharS | CharU | SChar | UChar | Short | UShort | Int | UInt | Long | ULong | LongLong
+        | ULongLong | Float | Double | LongDouble => Some("0"),
+        Pointer => Some("nullptr"),
+        _ if ty.get_display_name().starts_with("std::optional<") => Some("std::nullopt"),
+        _ => None,
+    }
+    .map(str::to_string)
+}
+
+/// Standard library class templates whose instances can be moved but not copied.
+const MOVE_ONLY_STD_TYPES: &[&str] = &[
+    "unique_ptr",
+    "unique_lock",
+    "thread",
+    "promise",
+    "future",
+    "packaged_task",
+];
+
+/// Whether values of the type can be moved but not copied. Besides well-known standard library
+/// types, this recognizes classes with a deleted copy constructor, or a move constructor and no
+/// copy constructor at all.
+pub fn is_move_only_type(ty: Type) -> bool {
+    let declaration = match ty.get_canonical_type().get_declaration() {
+        Some(declaration) => declaration,
+        None => return false,
+    };
+    let outermost_namespace = declaration
+        .semantic_parents()
+        .filter(|e| e.get_kind() == EntityKind::Namespace)
+        .last()
+        .and_then(|e| e.get_name());
+    if outermost_namespace.as_deref() == Some("std") {
+        if let Some(name) = declaration.get_name() {
+            if MOVE_ONLY_STD_TYPES.contains(&&name[..]) {
+                return true;
+            }
+        }
+    }
+
+    let constructors: Vec<_> = declaration
+        .get_definition()
+        .map(|definition| definition.get_children())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|e| e.get_kind() == EntityKind::Constructor)
+        .collect();
+    match constructors.iter().find(|c| c.is_copy_constructor()) {
+        Some(&copy_constructor) => is_deleted_method(copy_constructor),
+        None => constructors.iter().any(|c| c.is_move_constructor()),
+    }
+}
+
+/// Whether the method is explicitly deleted, i.e. declared with `= delete`.
+pub fn is_deleted_method(method: Entity) -> bool {
+    match method.get_range() {
+        Some(range) => range
+            .tokenize()
+            .iter()
+            .tuple_windows()
+            .any(|(eq, delete)| eq.get_spelling() == "=" && delete.get_spelling() == "delete"),
+        None => false,
+    }
+}
+
 fn format_mock_method_definition(method: Entity) -> String {
     let attributes = [
         get_method_const_qualifier(&method),
@@ -118,7 +688,7 @@ fn format_mock_method_definition(method: Entity) -> String {
     )
 }
 
-fn get_method_const_qualifier(method: &Entity) -> Option<&'static str> {
+pub fn get_method_const_qualifier(method: &Entity) -> Option<&'static str> {
     if method.is_const_method() {
         Some("const")
     } else {
@@ -127,24 +697,31 @@ fn get_method_const_qualifier(method: &Entity) -> Option<&'static str> {
 }
 
 fn get_method_value_category_qualifier(method: &Entity) -> Option<&'static str> {
+    get_method_ref_qualifier(method).map(|q| match q {
+        "&" => "ref(&)",
+        _ => "ref(&&)",
+    })
+}
+
+pub fn get_method_ref_qualifier(method: &Entity) -> Option<&'static str> {
     method
         .get_type()
         .unwrap()
         .get_ref_qualifier()
         .map(|q| match q {
-            RefQualifier::LValue => "ref(&)",
-            RefQualifier::RValue => "ref(&&)",
+            RefQualifier::LValue => "&",
+            RefQualifier::RValue => "&&",
         })
 }
 
-fn get_method_exception_specification(method: &Entity) -> Option<&'static str> {
+pub fn get_method_exception_specification(method: &Entity) -> Option<&'static str> {
     method.get_exception_specification().and_then(|e| match e {
         ExceptionSpecification::BasicNoexcept => Some("noexcept"),
         _ => None,
     })
 }
 
-fn get_method_parameter_type_list(method: &Entity) -> Vec<String> {
+pub fn get_method_parameter_type_list(method: &Entity) -> Vec<String> {
     method
         .get_arguments()
         .unwrap()
@@ -163,7 +740,7 @@ fn protect_commas(mut ty: String) -> String {
     ty
 }
 
-fn get_qualified_name(entity: Entity) -> String {
+pub fn get_qualified_name(entity: Entity) -> String {
     itertools::Itertools::intersperse(
         entity
             .semantic_parents()
@@ -176,7 +753,7 @@ fn get_qualified_name(entity: Entity) -> String {
     .collect()
 }
 
-fn get_type_spelling(e: Entity) -> Option<String> {
+pub fn get_type_spelling(e: Entity) -> Option<String> {
     e.get_range().map(|r| {
         r.tokenize()
             .into_iter()
@@ -263,6 +840,77 @@ mod class_entity_location_tests {
         );
     }
 
+    const AMBIGUOUS_SOURCE: &str = r#"
+        struct Storage {};
+        namespace net {
+            inline namespace v1 {
+                struct Storage {};
+            }
+            struct Client {
+                struct Storage;
+            };
+            struct Client::Storage {};
+        }
+    "#;
+
+    fn find_qualified_names(class_name: &str) -> Vec<String> {
+        test_tu_from_source(AMBIGUOUS_SOURCE, |tu| {
+            find_class_entities(tu, class_name)
+                .into_iter()
+                .map(get_qualified_name)
+                .collect()
+        })
+    }
+
+    #[test]
+    fn unqualified_name_matches_classes_in_all_scopes() {
+        assert_eq!(
+            find_qualified_names("Storage"),
+            vec!["Storage", "net::v1::Storage", "net::Client::Storage"]
+        );
+    }
+
+    #[test]
+    fn qualified_name_matches_enclosing_scopes() {
+        assert_eq!(
+            find_qualified_names("net::Storage"),
+            vec!["net::v1::Storage"]
+        );
+        assert_eq!(
+            find_qualified_names("v1::Storage"),
+            vec!["net::v1::Storage"]
+        );
+        assert_eq!(
+            find_qualified_names("Client::Storage"),
+            vec!["net::Client::Storage"]
+        );
+    }
+
+    #[test]
+    fn leading_double_colon_anchors_name_at_global_scope() {
+        assert_eq!(find_qualified_names("::Storage"), vec!["Storage"]);
+        assert_eq!(find_qualified_names("::net::Client"), vec!["net::Client"]);
+        assert!(find_qualified_names("::Client").is_empty());
+    }
+
+    #[test]
+    fn class_entity_is_resolved_to_definition() {
+        test_tu_from_source(
+            r#"
+                struct Foo;
+                struct Foo {
+                    int foo();
+                };
+            "#,
+            |tu| {
+                let class = find_class_entity(tu, "Foo").expect("Class entity was not found!");
+                assert!(class.is_definition());
+                let loc = class.get_location().unwrap().get_file_location();
+                assert_eq!(loc.line, 3);
+            },
+        );
+    }
+
     #[test]
     fn class_entity_is_not_found() {
         test_tu_from_source(
@@ -278,6 +926,156 @@ mod class_entity_location_tests {
     }
 }
 
+#[cfg(test)]
+mod abstract_class_search_tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn all_abstract_classes_are_found() {
+        test_tu_from_source(
+            r#"
+                struct Foo {
+                    virtual void foo() = 0;
+                };
+                struct Concrete : Foo {
+                    void foo() override;
+                };
+                namespace Bar {
+                    struct Baz;
+                    struct Baz : Foo {
+                        virtual void baz() const = 0;
+                        struct Nested {
+                            virtual int nested() = 0;
+                        };
+                    };
+                }
+            "#,
+            |tu| {
+                itertools::assert_equal(
+                    find_abstract_classes(tu)
+                        .into_iter()
+                        .map(|e| e.get_display_name().unwrap()),
+                    vec!["Foo", "Baz", "Nested"],
+                );
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod unmockable_reason_tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    fn test_unmockable_reason(source: &str, expected: Option<UnmockableReason>) {
+        test_class_from_source(source, "Foo", |class| {
+            assert_eq!(get_unmockable_reason(class), expected);
+        })
+    }
+
+    #[test]
+    fn interface_with_virtual_destructor_is_mockable() {
+        test_unmockable_reason(
+            r#"
+                struct Base {
+                    virtual ~Base() = default;
+                };
+                struct Foo : Base {
+                    virtual void foo() = 0;
+                };
+            "#,
+            None,
+        );
+    }
+
+    #[test]
+    fn final_class_is_unmockable() {
+        test_unmockable_reason(
+            r#"
+                struct Foo final {
+                    virtual ~Foo() = default;
+                    virtual void foo();
+                };
+            "#,
+            Some(UnmockableReason::Final),
+        );
+    }
+
+    #[test]
+    fn class_without_virtual_methods_is_unmockable() {
+        test_unmockable_reason(
+            r#"
+                struct Foo {
+                    virtual ~Foo() = default;
+                    void foo();
+                };
+            "#,
+            Some(UnmockableReason::NoVirtualMethods),
+        );
+    }
+
+    #[test]
+    fn non_virtual_destructor_prevails_over_missing_virtual_methods() {
+        test_class_from_source(
+            r#"
+                struct Foo {
+                    void foo();
+                };
+            "#,
+            "Foo",
+            |class| {
+                let reason = get_unmockable_reason(class).unwrap();
+                assert_eq!(reason, UnmockableReason::NonVirtualDestructor);
+                // `--allow-empty` does not apply to fatal reasons.
+                assert!(reason.is_fatal());
+            },
+        )
+    }
+
+    #[test]
+    fn public_non_virtual_destructor_is_unmockable() {
+        test_unmockable_reason(
+            r#"
+                struct Foo {
+                    virtual void foo() = 0;
+                };
+            "#,
+            Some(UnmockableReason::NonVirtualDestructor),
+        );
+    }
+
+    #[test]
+    fn protected_non_virtual_destructor_is_mockable() {
+        test_unmockable_reason(
+            r#"
+                struct Foo {
+                    virtual void foo() = 0;
+                protected:
+                    ~Foo() = default;
+                };
+            "#,
+            None,
+        );
+    }
+
+    #[test]
+    fn class_with_all_methods_implemented_is_unmockable() {
+        test_unmockable_reason(
+            r#"
+                struct Base {
+                    virtual ~Base() = default;
+                    virtual void foo() = 0;
+                };
+                struct Foo : Base {
+                    void foo() override;
+                };
+            "#,
+            Some(UnmockableReason::AllMethodsImplemented),
+        );
+    }
+}
+
 #[cfg(test)]
 mod find_base_classes_tests {
     use super::*;
@@ -458,28 +1256,7 @@ mod find_base_classes_tests {
 #[cfg(test)]
 mod mock_method_tests {
     use super::*;
-
-    fn test_method_for_function<C: Fn(Entity)>(func_decl: &str, callback: C) {
-        crate::test_utils::test_tu_from_source(
-            &format!(
-                r#"
-                    template <typename T, typename U> struct pair;
-                    namespace std {{ struct string; }}
-                    struct TestClass {{ {} }};
-                "#,
-                func_decl
-            ),
-            |tu| {
-                callback(
-                    *find_class_entity(tu, "TestClass")
-                        .unwrap()
-                        .get_children()
-                        .first()
-                        .unwrap(),
-                )
-            },
-        )
-    }
+    use crate::test_utils::test_method_for_function;
 
     fn assert_mock_for_function(func_decl: &str, mock_decl: &str) {
         test_method_for_function(func_decl, |method| {
@@ -657,7 +1434,7 @@ mod generate_mock_class_from_interface {
     fn mock_class_inherits_from_class() {
         test_class_from_source("struct Foo;", "Foo", |class| {
             assert_eq_upto_whitespace(
-                &generate_mock_definition(class, "FooMock"),
+                &generate_mock_definition(class, "FooMock", &MockOptions::default()),
                 "struct FooMock : Foo {};",
             )
         });
@@ -667,7 +1444,7 @@ mod generate_mock_class_from_interface {
     fn mock_class_inherits_from_class_in_namespace() {
         test_class_from_source("namespace Bar { struct Foo; }", "Foo", |class| {
             assert_eq_upto_whitespace(
-                &generate_mock_definition(class, "FooMock"),
+                &generate_mock_definition(class, "FooMock", &MockOptions::default()),
                 "struct FooMock : Bar::Foo {};",
             )
         })
@@ -677,7 +1454,7 @@ mod generate_mock_class_from_interface {
     fn mock_class_inherits_from_nested_class() {
         test_class_from_source("struct Bar { struct Foo; };", "Foo", |class| {
             assert_eq_upto_whitespace(
-                &generate_mock_definition(class, "FooMock"),
+                &generate_mock_definition(class, "FooMock", &MockOptions::default()),
                 "struct FooMock : Bar::Foo {};",
             )
         })
@@ -696,7 +1473,7 @@ mod generate_mock_class_from_interface {
             "Foo",
             |class| {
                 assert_eq_upto_whitespace(
-                    &generate_mock_definition(class, "FooMock"),
+                    &generate_mock_definition(class, "FooMock", &MockOptions::default()),
                     r#"
                         struct FooMock : Foo {
                             MOCK_METHOD(void, baz, (), (override));
@@ -720,7 +1497,7 @@ mod generate_mock_class_from_interface {
             "Foo",
             |class| {
                 assert_eq_upto_whitespace(
-                    &generate_mock_definition(class, "FooMock"),
+                    &generate_mock_definition(class, "FooMock", &MockOptions::default()),
                     r#"
                         struct FooMock : Foo {
                             MOCK_METHOD(void, baz, (), (noexcept, override));
@@ -752,7 +1529,7 @@ mod generate_mock_class_from_interface {
             "Foo",
             |class| {
                 assert_eq_upto_whitespace(
-                    &generate_mock_definition(class, "FooMock"),
+                    &generate_mock_definition(class, "FooMock", &MockOptions::default()),
                     r#"
                         struct FooMock : Foo {
                             MOCK_METHOD(void, baz, (), (noexcept, override));
@@ -786,7 +1563,7 @@ mod generate_mock_class_from_interface {
             "Foo",
             |class| {
                 assert_eq_upto_whitespace(
-                    &generate_mock_definition(class, "FooMock"),
+                    &generate_mock_definition(class, "FooMock", &MockOptions::default()),
                     r#"
                         struct FooMock : Foo {
                             MOCK_METHOD(void, foo, (), (override));
@@ -812,7 +1589,7 @@ mod generate_mock_class_from_interface {
             "Foo",
             |class| {
                 assert_eq_upto_whitespace(
-                    &generate_mock_definition(class, "FooMock"),
+                    &generate_mock_definition(class, "FooMock", &MockOptions::default()),
                     r#"
                     struct FooMock : Bar::Foo {
                         MOCK_METHOD(void, foo, (Bar::Param), (override));
@@ -838,7 +1615,7 @@ mod generate_mock_class_from_interface {
             "Foo",
             |class| {
                 assert_eq_upto_whitespace(
-                    &generate_mock_definition(class, "FooMock"),
+                    &generate_mock_definition(class, "FooMock", &MockOptions::default()),
                     r#"
                     struct FooMock : Bar::Foo {
                         MOCK_METHOD(Bar::Param, foo, (), (override));
@@ -848,4 +1625,365 @@ mod generate_mock_class_from_interface {
             },
         )
     }
+
+    #[test]
+    fn mock_aliases_are_emitted_after_class() {
+        test_class_from_source(
+            r#"
+                struct Foo {
+                    virtual void foo() = 0;
+                };
+            "#,
+            "Foo",
+            |class| {
+                assert_eq_upto_whitespace(
+                    &generate_mock_definition(
+                        class,
+                        "FooMock",
+                        &MockOptions {
+                            nice_alias: true,
+                            strict_alias: true,
+                            ..Default::default()
+                        },
+                    ),
+                    r#"
+                        struct FooMock : Foo {
+                            MOCK_METHOD(void, foo, (), (override));
+                        };
+
+                        using NiceFooMock = ::testing::NiceMock<FooMock>;
+                        using StrictFooMock = ::testing::StrictMock<FooMock>;
+                    "#,
+                )
+            },
+        )
+    }
+
+    #[test]
+    fn default_actions_are_installed_for_configured_return_types_only() {
+        test_class_from_source(
+            r#"
+                struct Param {};
+                struct Foo {
+                    virtual bool foo(int, Param) = 0;
+                    virtual void bar() = 0;
+                    virtual Param baz() const = 0;
+                    virtual const char* qux() = 0;
+                };
+            "#,
+            "Foo",
+            |class| {
+                assert_eq_upto_whitespace(
+                    &generate_mock_definition(
+                        class,
+                        "FooMock",
+                        &MockOptions {
+                            default_returns: vec![
+                                ("bool".to_string(), "true".to_string()),
+                                ("Param".to_string(), "Param{}".to_string()),
+                            ],
+                            ..Default::default()
+                        },
+                    ),
+                    r#"
+                        struct FooMock : Foo {
+                            FooMock() {
+                                ON_CALL(*this, foo(::testing::_, ::testing::_)).WillByDefault(::testing::Return(true));
+                                ON_CALL(*this, baz()).WillByDefault(::testing::Return(Param{}));
+                            }
+
+                            MOCK_METHOD(bool, foo, (int, Param), (override));
+                            MOCK_METHOD(void, bar, (), (override));
+                            MOCK_METHOD(Param, baz, (), (const, override));
+                            MOCK_METHOD(const char *, qux, (), (override));
+                        };
+                    "#,
+                )
+            },
+        )
+    }
+
+    #[test]
+    fn default_actions_for_overloads_use_typed_matchers() {
+        test_class_from_source(
+            r#"
+                struct Foo {
+                    virtual int foo(int) = 0;
+                    virtual int foo(double) = 0;
+                };
+            "#,
+            "Foo",
+            |class| {
+                assert_eq_upto_whitespace(
+                    &generate_mock_definition(
+                        class,
+                        "FooMock",
+                        &MockOptions {
+                            default_returns: vec![("int".to_string(), "42".to_string())],
+                            ..Default::default()
+                        },
+                    ),
+                    r#"
+                        struct FooMock : Foo {
+                            FooMock() {
+                                ON_CALL(*this, foo(::testing::A<int>())).WillByDefault(::testing::Return(42));
+                                ON_CALL(*this, foo(::testing::A<double>())).WillByDefault(::testing::Return(42));
+                            }
+
+                            MOCK_METHOD(int, foo, (int), (override));
+                            MOCK_METHOD(int, foo, (double), (override));
+                        };
+                    "#,
+                )
+            },
+        )
+    }
+
+    #[test]
+    fn delegating_actions_forward_to_fake() {
+        test_class_from_source(
+            r#"
+                struct Foo {
+                    virtual int foo(int x) = 0;
+                    virtual int bar() const = 0;
+                    virtual int bar() = 0;
+                    virtual void baz() && = 0;
+                };
+            "#,
+            "Foo",
+            |class| {
+                assert_eq_upto_whitespace(
+                    &generate_mock_definition(
+                        class,
+                        "FooMock",
+                        &MockOptions {
+                            delegate_to: Some("FakeFoo".to_string()),
+                            ..Default::default()
+                        },
+                    ),
+                    r#"
+                        struct FooMock : Foo {
+                            void DelegateToFake() {
+                                ON_CALL(*this, foo(::testing::_)).WillByDefault([this](auto&&... args) -> decltype(auto) {
+                                    return fake_.foo(std::forward<decltype(args)>(args)...);
+                                });
+                                ON_CALL(std::as_const(*this), bar()).WillByDefault([this](auto&&... args) -> decltype(auto) {
+                                    return std::as_const(fake_).bar(std::forward<decltype(args)>(args)...);
+                                });
+                                ON_CALL(*this, bar()).WillByDefault([this](auto&&... args) -> decltype(auto) {
+                                    return fake_.bar(std::forward<decltype(args)>(args)...);
+                                });
+                                ON_CALL(std::move(*this), baz()).WillByDefault([this](auto&&... args) -> decltype(auto) {
+                                    return std::move(fake_).baz(std::forward<decltype(args)>(args)...);
+                                });
+                            }
+
+                            MOCK_METHOD(int, foo, (int), (override));
+                            MOCK_METHOD(int, bar, (), (const, override));
+                            MOCK_METHOD(int, bar, (), (override));
+                            MOCK_METHOD(void, baz, (), (ref(&&), override));
+
+                        private:
+                            FakeFoo fake_;
+                        };
+                    "#,
+                )
+            },
+        )
+    }
+
+    #[test]
+    fn callbacks_are_captured_and_can_be_invoked() {
+        test_class_from_source(
+            r#"
+                namespace std { template <typename> struct function; }
+                struct Event {};
+                struct Foo {
+                    virtual void subscribe(int id, std::function<void(Event)> const& callback) = 0;
+                    virtual bool unsubscribe(int id) = 0;
+                };
+            "#,
+            "Foo",
+            |class| {
+                assert_eq_upto_whitespace(
+                    &generate_mock_definition(
+                        class,
+                        "FooMock",
+                        &MockOptions {
+                            mock_callbacks: true,
+                            ..Default::default()
+                        },
+                    ),
+                    r#"
+                        struct FooMock : Foo {
+                            FooMock() {
+                                ON_CALL(*this, subscribe(::testing::_, ::testing::_)).WillByDefault(::testing::SaveArg<1>(&subscribeCallback));
+                            }
+
+                            MOCK_METHOD(void, subscribe, (int, std::function<void(Event)> const&), (override));
+                            MOCK_METHOD(bool, unsubscribe, (int), (override));
+
+                            ::testing::MockFunction<void(Event)> subscribeCallbackMock;
+                            std::function<void(Event)> subscribeCallback;
+                            template <typename... Args>
+                            decltype(auto) InvokeSubscribeCallback(Args&&... args) {
+                                subscribeCallbackMock.Call(args...);
+                                return subscribeCallback(std::forward<Args>(args)...);
+                            }
+                        };
+                    "#,
+                )
+            },
+        )
+    }
+
+    #[test]
+    fn callbacks_are_captured_regardless_of_return_type_and_overloads() {
+        test_class_from_source(
+            r#"
+                namespace std { template <typename> struct function; }
+                struct Handle {};
+                struct Foo {
+                    virtual Handle subscribe(std::function<void(int)> callback) = 0;
+                    virtual Handle subscribe(std::function<void(double)> callback) = 0;
+                };
+            "#,
+            "Foo",
+            |class| {
+                assert_eq_upto_whitespace(
+                    &generate_mock_definition(
+                        class,
+                        "FooMock",
+                        &MockOptions {
+                            mock_callbacks: true,
+                            ..Default::default()
+                        },
+                    ),
+                    r#"
+                        struct FooMock : Foo {
+                            FooMock() {
+                                ON_CALL(*this, subscribe(::testing::A<std::function<void(int)>>())).WillByDefault(::testing::DoAll(::testing::SaveArg<0>(&subscribeCallback), ::testing::InvokeWithoutArgs([]() -> Handle { return ::testing::DefaultValue<Handle>::Get(); })));
+                                ON_CALL(*this, subscribe(::testing::A<std::function<void(double)>>())).WillByDefault(::testing::DoAll(::testing::SaveArg<0>(&subscribe2Callback), ::testing::InvokeWithoutArgs([]() -> Handle { return ::testing::DefaultValue<Handle>::Get(); })));
+                            }
+
+                            MOCK_METHOD(Handle, subscribe, (std::function<void(int)>), (override));
+                            MOCK_METHOD(Handle, subscribe, (std::function<void(double)>), (override));
+
+                            ::testing::MockFunction<void(int)> subscribeCallbackMock;
+                            std::function<void(int)> subscribeCallback;
+                            template <typename... Args>
+                            decltype(auto) InvokeSubscribeCallback(Args&&... args) {
+                                subscribeCallbackMock.Call(args...);
+                                return subscribeCallback(std::forward<Args>(args)...);
+                            }
+
+                            ::testing::MockFunction<void(double)> subscribe2CallbackMock;
+                            std::function<void(double)> subscribe2Callback;
+                            template <typename... Args>
+                            decltype(auto) InvokeSubscribe2Callback(Args&&... args) {
+                                subscribe2CallbackMock.Call(args...);
+                                return subscribe2Callback(std::forward<Args>(args)...);
+                            }
+                        };
+                    "#,
+                )
+            },
+        )
+    }
+
+    #[test]
+    fn expect_call_snippets_have_one_matcher_per_parameter() {
+        test_class_from_source(
+            r#"
+                template <typename T, typename U> struct map;
+                struct string;
+                struct Foo {
+                    virtual bool open(string const& path, int mode) = 0;
+                    virtual void close() = 0;
+                    virtual auto list() const -> map<string, int> = 0;
+                    virtual string const& name() const = 0;
+                    virtual string&& take() = 0;
+                };
+            "#,
+            "Foo",
+            |class| {
+                itertools::assert_equal(
+                    generate_expect_call_snippets(class, "fooMock", None),
+                    vec![
+                        "EXPECT_CALL(fooMock, open(::testing::_, ::testing::_)).WillOnce(::testing::Return(false));",
+                        "EXPECT_CALL(fooMock, close());",
+                        "EXPECT_CALL(fooMock, list()).WillOnce(::testing::Return(map<string, int>{}));",
+                        "EXPECT_CALL(fooMock, name()).WillOnce(::testing::ReturnRef(/* string */));",
+                        "EXPECT_CALL(fooMock, take()); // TODO: add an action returning string &&",
+                    ],
+                )
+            },
+        )
+    }
+
+    #[test]
+    fn expect_call_snippets_can_be_restricted_to_single_method() {
+        test_class_from_source(
+            r#"
+                struct Foo {
+                    virtual int foo(int) = 0;
+                    virtual int bar(int) = 0;
+                };
+            "#,
+            "Foo",
+            |class| {
+                itertools::assert_equal(
+                    generate_expect_call_snippets(class, "mock", Some("bar")),
+                    vec!["EXPECT_CALL(mock, bar(::testing::_)).WillOnce(::testing::Return(0));"],
+                )
+            },
+        )
+    }
+
+    #[test]
+    fn expect_call_snippets_disambiguate_overloads() {
+        test_class_from_source(
+            r#"
+                struct Foo {
+                    virtual void foo(int) = 0;
+                    virtual void foo(double) const = 0;
+                };
+            "#,
+            "Foo",
+            |class| {
+                itertools::assert_equal(
+                    generate_expect_call_snippets(class, "mock", None),
+                    vec![
+                        "EXPECT_CALL(mock, foo(::testing::A<int>()));",
+                        "EXPECT_CALL(std::as_const(mock), foo(::testing::A<double>()));",
+                    ],
+                )
+            },
+        )
+    }
+
+    #[test]
+    fn expect_call_snippets_return_move_only_types_by_move() {
+        test_class_from_source(
+            r#"
+                namespace std {
+                    template <typename T> struct unique_ptr {
+                        unique_ptr(unique_ptr&&);
+                    };
+                }
+                struct Foo {
+                    virtual std::unique_ptr<int> foo() = 0;
+                };
+            "#,
+            "Foo",
+            |class| {
+                itertools::assert_equal(
+                    generate_expect_call_snippets(class, "mock", None),
+                    vec![
+                        "EXPECT_CALL(mock, foo()).WillOnce(::testing::Return(::testing::ByMove(std::unique_ptr<int>{})));",
+                    ],
+                )
+            },
+        )
+    }
 }
@@ -0,0 +1,270 @@
+use std::path::{Component, Path, PathBuf};
+
+/// A piece of text to be inserted into a source file at the given (1-based) line and column.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Insertion {
+    pub line: u32,
+    pub column: u32,
+    pub text: String,
+}
+
+pub fn apply_insertions(source: &str, insertions: &[Insertion]) -> String {
+    let mut insertions = insertions.to_vec();
+    insertions.sort_by_key(|ins| (ins.line, ins.column));
+
+    let mut lines: Vec<String> = source.split('\n').map(str::to_string).collect();
+    for ins in insertions.iter().rev() {
+        if let Some(line) = lines.get_mut(ins.line as usize - 1) {
+            let offset = (ins.column as usize - 1).min(line.len());
+            line.insert_str(offset, &ins.text);
+        }
+    }
+
+    lines.join("\n")
+}
+
+/// A line of a diff, including its line terminator.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DiffLine<'a> {
+    Context(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Formats a unified diff between two versions of a file. Lines retain their terminators, so that
+/// the patch applies cleanly to files with `\r\n` line endings as well.
+pub fn format_unified_diff(path: &str, old: &str, new: &str) -> String {
+    const CONTEXT: usize = 3;
+
+    let old_lines: Vec<_> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<_> = new.split_inclusive('\n').collect();
+    let lines = diff_lines(&old_lines, &new_lines);
+
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        if let DiffLine::Context(_) = line {
+            continue;
+        }
+        let start = i.saturating_sub(CONTEXT);
+        let end = (i + CONTEXT + 1).min(lines.len());
+        match hunks.last_mut() {
+            Some(last) if last.1 >= start => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+    if hunks.is_empty() {
+        return String::new();
+    }
+
+    let mut diff = format!("--- a/{}\n+++ b/{}\n", path, path);
+    let (mut old_line, mut new_line, mut position) = (1, 1, 0);
+    for (start, end) in hunks {
+        for line in &lines[position..start] {
+            match line {
+                DiffLine::Context(_) => {
+                    old_line += 1;
+                    new_line += 1;
+                }
+                DiffLine::Removed(_) => old_line += 1,
+                DiffLine::Added(_) => new_line += 1,
+            }
+        }
+        let hunk = &lines[start..end];
+        let old_count = hunk
+            .iter()
+            .filter(|line| !matches!(line, DiffLine::Added(_)))
+            .count();
+        let new_count = hunk
+            .iter()
+            .filter(|line| !matches!(line, DiffLine::Removed(_)))
+            .count();
+        diff.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            old_line, old_count, new_line, new_count
+        ));
+        for line in hunk {
+            let (prefix, text) = match line {
+                DiffLine::Context(text) => (' ', text),
+                DiffLine::Removed(text) => ('-', text),
+                DiffLine::Added(text) => ('+', text),
+            };
+            diff.push(prefix);
+            diff.push_str(text);
+            if !text.ends_with('\n') {
+                diff.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+        old_line += old_count;
+        new_line += new_count;
+        position = end;
+    }
+
+    diff
+}
+
+/// Computes the shortest sequence of removed and added lines turning `old` into `new`, using
+/// Myers' algorithm.
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<DiffLine<'a>> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let offset = n + m + 1;
+    let index = |k: isize| (k + offset) as usize;
+    let mut v = vec![0; 2 * offset as usize + 1];
+    let mut trace = Vec::new();
+    'search: for d in 0..=n + m {
+        trace.push(v.clone());
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]) {
+                v[index(k + 1)]
+            } else {
+                v[index(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index(k)] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let previous_k = if k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let previous_x = v[index(previous_k)];
+        let previous_y = previous_x - previous_k;
+        while x > previous_x && y > previous_y {
+            lines.push(DiffLine::Context(old[x as usize - 1]));
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            if x == previous_x {
+                lines.push(DiffLine::Added(new[y as usize - 1]));
+            } else {
+                lines.push(DiffLine::Removed(old[x as usize - 1]));
+            }
+        }
+        x = previous_x;
+        y = previous_y;
+    }
+    lines.reverse();
+    lines
+}
+
+/// Expresses a path relative to the given base directory, which is how paths appear in a patch to
+/// be applied with `git apply` or `patch -p1` from that directory.
+pub fn get_relative_path(path: &Path, base: &Path) -> PathBuf {
+    let common = path
+        .components()
+        .zip(base.components())
+        .take_while(|(lhs, rhs)| lhs == rhs)
+        .count();
+    base.components()
+        .skip(common)
+        .map(|_| Component::ParentDir)
+        .chain(path.components().skip(common))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn insertions_are_applied_at_line_and_column() {
+        assert_eq!(
+            apply_insertions(
+                "int foo();\nint bar();\n",
+                &[
+                    Insertion {
+                        line: 2,
+                        column: 10,
+                        text: " const".to_string(),
+                    },
+                    Insertion {
+                        line: 1,
+                        column: 1,
+                        text: "virtual ".to_string(),
+                    },
+                    Insertion {
+                        line: 2,
+                        column: 10,
+                        text: " noexcept".to_string(),
+                    },
+                ],
+            ),
+            "virtual int foo();\nint bar() const noexcept;\n"
+        );
+    }
+
+    #[test]
+    fn diff_of_identical_files_is_empty() {
+        assert_eq!(format_unified_diff("foo.h", "a\nb\n", "a\nb\n"), "");
+    }
+
+    #[test]
+    fn diff_contains_context_around_changes() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n13\n14\n";
+        let new = "1\n2\n3\n4\nfive\n6\n7\n8\n9\n10\n11\n12\n13\nfourteen\n";
+        assert_eq!(
+            format_unified_diff("foo.h", old, new),
+            "--- a/foo.h\n+++ b/foo.h\n\
+             @@ -2,7 +2,7 @@\n 2\n 3\n 4\n-5\n+five\n 6\n 7\n 8\n\
+             @@ -11,4 +11,4 @@\n 11\n 12\n 13\n-14\n+fourteen\n"
+        );
+    }
+
+    #[test]
+    fn adjacent_changes_are_merged_into_one_hunk() {
+        let old = "1\n2\n3\n4\n5\n";
+        let new = "1\ntwo\nthree\n4\nfive\n";
+        assert_eq!(
+            format_unified_diff("foo.h", old, new),
+            "--- a/foo.h\n+++ b/foo.h\n\
+             @@ -1,5 +1,5 @@\n 1\n-2\n-3\n+two\n+three\n 4\n-5\n+five\n"
+        );
+    }
+
+    #[test]
+    fn added_lines_shift_subsequent_hunks() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+        let new = "1\n2\nadded\n3\n4\n5\n6\n7\n8\n9\nten\n";
+        assert_eq!(
+            format_unified_diff("foo.h", old, new),
+            "--- a/foo.h\n+++ b/foo.h\n\
+             @@ -1,5 +1,6 @@\n 1\n 2\n+added\n 3\n 4\n 5\n\
+             @@ -7,4 +8,4 @@\n 7\n 8\n 9\n-10\n+ten\n"
+        );
+    }
+
+    #[test]
+    fn line_terminators_are_retained() {
+        assert_eq!(
+            format_unified_diff("foo.h", "a\r\nb\r\nc", "a\r\nB\r\nc"),
+            "--- a/foo.h\n+++ b/foo.h\n\
+             @@ -1,3 +1,3 @@\n a\r\n-b\r\n+B\r\n c\n\\ No newline at end of file\n"
+        );
+    }
+
+    #[test]
+    fn paths_are_made_relative_to_base_directory() {
+        assert_eq!(
+            get_relative_path(Path::new("/project/src/foo.h"), Path::new("/project")),
+            PathBuf::from("src/foo.h")
+        );
+        assert_eq!(
+            get_relative_path(Path::new("/project/src/foo.h"), Path::new("/project/build")),
+            PathBuf::from("../src/foo.h")
+        );
+    }
+}
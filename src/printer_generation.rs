@@ -0,0 +1,333 @@
+use clang::*;
+use itertools::Itertools;
+
+use crate::implementation_generation::wrap_in_namespaces;
+use crate::mock_generation::*;
+
+/// Generates `PrintTo` overloads and/or `operator==` for the user-defined aggregate types which
+/// appear as parameter or return types of the mocked methods of any of the interfaces, unless these
+/// are already declared. Aggregates nested as fields in such types are covered as well and precede
+/// them. Each type is handled once, even if it is used by several interfaces, and its helpers are
+/// enclosed in an include guard so that they can be repeated in separately generated headers.
+pub fn generate_parameter_type_helpers(
+    interface_classes: &[Entity],
+    print_to: bool,
+    equality: bool,
+) -> Vec<String> {
+    let mut types = Vec::new();
+    for &interface_class in interface_classes {
+        let mut methods: Vec<_> = get_abstract_methods(interface_class).collect();
+        methods.reverse();
+        for ty in methods.into_iter().flat_map(get_user_defined_types) {
+            collect_aggregates(ty, &mut types);
+        }
+    }
+
+    types
+        .into_iter()
+        .filter_map(|ty| {
+            let root = ty.get_translation_unit().get_entity();
+            let mut helpers = Vec::new();
+            if print_to && !has_free_function_for(root, &["PrintTo", "operator<<"], ty) {
+                helpers.push(format_print_to(ty));
+            }
+            if equality
+                && !has_free_function_for(root, &["operator=="], ty)
+                && !has_member_function(ty, "operator==")
+            {
+                helpers.push(format_equality_operator(ty));
+            }
+            if helpers.is_empty() {
+                None
+            } else {
+                Some(format_include_guard(
+                    ty,
+                    wrap_in_namespaces(ty, helpers.join("\n\n")),
+                ))
+            }
+        })
+        .collect()
+}
+
+/// Appends the aggregate and, before it, the aggregates among its field types, unless they have
+/// been collected already.
+fn collect_aggregates<'tu>(ty: Entity<'tu>, types: &mut Vec<Entity<'tu>>) {
+    if types.contains(&ty) || !is_aggregate(ty) {
+        return;
+    }
+    for field in ty
+        .get_children()
+        .into_iter()
+        .filter(|child| child.get_kind() == EntityKind::FieldDecl)
+        .filter_map(|field| field.get_type())
+        .filter_map(get_class_declaration)
+    {
+        collect_aggregates(field, types);
+    }
+    types.push(ty);
+}
+
+fn format_include_guard(class: Entity, code: String) -> String {
+    let guard = format!(
+        "MOCKERY_HELPERS_{}",
+        get_qualified_name(class).split("::").join("_")
+    );
+    format!("#ifndef {}\n#define {}\n\n{}\n\n#endif", guard, guard, code)
+}
+
+fn get_user_defined_types(method: Entity) -> Vec<Entity> {
+    method
+        .get_result_type()
+        .into_iter()
+        .chain(get_argument_types(method))
+        .filter_map(get_class_declaration)
+        .collect()
+}
+
+fn get_argument_types(function: Entity) -> Vec<Type> {
+    function
+        .get_arguments()
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|arg| arg.get_type())
+        .collect()
+}
+
+fn get_class_declaration(ty: Type) -> Option<Entity> {
+    let ty = ty.get_canonical_type();
+    let ty = match ty.get_kind() {
+        TypeKind::LValueReference | TypeKind::RValueReference => ty.get_pointee_type()?,
+        _ => ty,
+    };
+    ty.get_declaration()?
+        .get_definition()
+        .filter(|decl| !decl.is_in_system_header())
+}
+
+fn is_aggregate(class: Entity) -> bool {
+    use EntityKind::*;
+    matches!(class.get_kind(), StructDecl | ClassDecl)
+        && class
+            .get_children()
+            .iter()
+            .all(|child| match child.get_kind() {
+                Constructor => child.is_defaulted(),
+                BaseSpecifier => false,
+                Method => !child.is_virtual_method(),
+                FieldDecl => child.get_accessibility() == Some(Accessibility::Public),
+                _ => true,
+            })
+}
+
+fn get_field_names(class: Entity) -> Vec<String> {
+    class
+        .get_children()
+        .into_iter()
+        .filter(|child| child.get_kind() == EntityKind::FieldDecl)
+        .filter_map(|field| field.get_name())
+        .collect()
+}
+
+fn has_member_function(class: Entity, name: &str) -> bool {
+    class.get_children().iter().any(|child| {
+        child.get_kind() == EntityKind::Method && child.get_name().as_deref() == Some(name)
+    })
+}
+
+/// Searches the whole translation unit for a free function with one of the given names which
+/// takes the class as a parameter.
+fn has_free_function_for(root: Entity, names: &[&str], class: Entity) -> bool {
+    let mut found = false;
+    root.visit_children(|entity, _| match entity.get_kind() {
+        EntityKind::FunctionDecl => {
+            if names
+                .iter()
+                .any(|&name| entity.get_name().as_deref() == Some(name))
+                && get_argument_types(entity)
+                    .into_iter()
+                    .filter_map(get_class_declaration)
+                    .any(|ty| ty == class)
+            {
+                found = true;
+                EntityVisitResult::Break
+            } else {
+                EntityVisitResult::Continue
+            }
+        }
+        EntityKind::Namespace
+        | EntityKind::StructDecl
+        | EntityKind::ClassDecl
+        | EntityKind::FriendDecl
+        | EntityKind::LinkageSpec => EntityVisitResult::Recurse,
+        _ => EntityVisitResult::Continue,
+    });
+    found
+}
+
+fn format_print_to(class: Entity) -> String {
+    let fields: Vec<_> = get_field_names(class)
+        .into_iter()
+        .enumerate()
+        .map(|(i, field)| {
+            format!(
+                "\"{}{}: \" << ::testing::PrintToString(value.{})",
+                if i == 0 { "" } else { ", " },
+                field,
+                field
+            )
+        })
+        .collect();
+
+    format!(
+        "inline void PrintTo({} const& value, std::ostream* os) {{\n\t*os << \"{}{{\" << {}\"}}\";\n}}",
+        get_qualified_name(class),
+        class.get_name().unwrap(),
+        fields
+            .iter()
+            .map(|field| format!("{} << ", field))
+            .collect::<String>()
+    )
+}
+
+fn format_equality_operator(class: Entity) -> String {
+    let comparisons: Vec<_> = get_field_names(class)
+        .into_iter()
+        .map(|field| format!("lhs.{} == rhs.{}", field, field))
+        .collect();
+
+    format!(
+        "inline bool operator==({ty} const& lhs, {ty} const& rhs) {{\n\treturn {};\n}}",
+        if comparisons.is_empty() {
+            "true".to_string()
+        } else {
+            comparisons.join(" && ")
+        },
+        ty = get_qualified_name(class)
+    )
+}
+
+#[cfg(test)]
+mod parameter_type_helper_tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    const SOURCE: &str = r#"
+        namespace std { struct ostream; }
+        namespace Bar {
+            struct Param {
+                int x;
+                double y;
+            };
+            struct Printable {
+                int z;
+            };
+            void PrintTo(const Printable&, std::ostream*);
+            class Opaque {
+                int secret;
+            };
+        }
+        struct Foo {
+            virtual Bar::Param foo(Bar::Printable const& p, Bar::Opaque o) = 0;
+            virtual void bar(int, Bar::Param&&) = 0;
+        };
+    "#;
+
+    #[test]
+    fn print_to_is_generated_for_aggregates_lacking_it() {
+        test_class_from_source(SOURCE, "Foo", |class| {
+            let helpers = generate_parameter_type_helpers(&[class], true, false);
+            assert_eq!(helpers.len(), 1);
+            assert_eq_upto_whitespace(
+                &helpers[0],
+                r#"
+                    #ifndef MOCKERY_HELPERS_Bar_Param
+                    #define MOCKERY_HELPERS_Bar_Param
+
+                    namespace Bar {
+
+                    inline void PrintTo(Bar::Param const& value, std::ostream* os) {
+                        *os << "Param{" << "x: " << ::testing::PrintToString(value.x) << ", y: " << ::testing::PrintToString(value.y) << "}";
+                    }
+
+                    } // namespace Bar
+
+                    #endif
+                "#,
+            );
+        })
+    }
+
+    #[test]
+    fn equality_operator_compares_all_fields() {
+        test_class_from_source(SOURCE, "Foo", |class| {
+            let helpers = generate_parameter_type_helpers(&[class], false, true);
+            assert_eq!(helpers.len(), 2);
+            assert_eq_upto_whitespace(
+                &helpers[0],
+                r#"
+                    #ifndef MOCKERY_HELPERS_Bar_Param
+                    #define MOCKERY_HELPERS_Bar_Param
+
+                    namespace Bar {
+
+                    inline bool operator==(Bar::Param const& lhs, Bar::Param const& rhs) {
+                        return lhs.x == rhs.x && lhs.y == rhs.y;
+                    }
+
+                    } // namespace Bar
+
+                    #endif
+                "#,
+            );
+        })
+    }
+
+    #[test]
+    fn helpers_for_types_shared_by_interfaces_are_generated_once() {
+        test_tu_from_source(
+            r#"
+                struct Param {
+                    int x;
+                };
+                struct Foo {
+                    virtual void foo(Param) = 0;
+                };
+                struct Bar {
+                    virtual Param bar() = 0;
+                };
+            "#,
+            |tu| {
+                let foo = find_class_entity(tu, "Foo").unwrap();
+                let bar = find_class_entity(tu, "Bar").unwrap();
+                let helpers = generate_parameter_type_helpers(&[foo, bar], false, true);
+                assert_eq!(helpers.len(), 1);
+                assert!(helpers[0].starts_with("#ifndef MOCKERY_HELPERS_Param\n"));
+            },
+        );
+    }
+
+    #[test]
+    fn helpers_for_nested_aggregates_precede_enclosing_ones() {
+        test_class_from_source(
+            r#"
+                struct Inner {
+                    int x;
+                };
+                struct Outer {
+                    Inner inner;
+                    int* y;
+                };
+                struct Foo {
+                    virtual void foo(Outer const&) = 0;
+                };
+            "#,
+            "Foo",
+            |class| {
+                let helpers = generate_parameter_type_helpers(&[class], false, true);
+                assert_eq!(helpers.len(), 2);
+                assert!(helpers[0].contains("operator==(Inner const& lhs"));
+                assert!(helpers[1].contains("operator==(Outer const& lhs"));
+            },
+        );
+    }
+}
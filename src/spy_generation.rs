@@ -0,0 +1,366 @@
+use clang::*;
+
+use crate::mock_generation::*;
+
+pub fn generate_spy_definition(interface_class: Entity, spy_class_name: &str) -> String {
+    let mut methods: Vec<_> = get_abstract_methods(interface_class).collect();
+    methods.reverse();
+
+    let spy_methods: Vec<_> = methods
+        .iter()
+        .map(|method| format_spy_method_definition(*method, &get_overload_name(method, &methods)))
+        .collect();
+
+    format!(
+        "struct {} : {} {{\n\t{}\n}};",
+        spy_class_name,
+        get_qualified_name(interface_class),
+        spy_methods.join("\n\n\t")
+    )
+}
+
+fn format_spy_method_definition(method: Entity, recorder_name: &str) -> String {
+    let parameter_types = get_method_parameter_type_list(&method);
+    let parameter_names = get_parameter_names(&method);
+    let result_type = method.get_result_type().unwrap().get_display_name();
+    let qualifiers = [
+        get_method_const_qualifier(&method),
+        get_method_ref_qualifier(&method),
+        get_method_exception_specification(&method),
+        Some("override"),
+    ];
+
+    let mut lines = vec![format!(
+        "mutable std::vector<std::tuple<{}>> {}_calls;",
+        parameter_types
+            .iter()
+            .map(|ty| get_storable_type(ty))
+            .collect::<Vec<_>>()
+            .join(", "),
+        recorder_name
+    )];
+    let result = get_result_storage(
+        method.get_result_type().unwrap(),
+        &format!("{}_result", recorder_name),
+    );
+    if let Some(result) = &result {
+        lines.push(result.declaration.clone());
+    }
+    lines.push(format!(
+        "{} {}({}) {} {{",
+        result_type,
+        method.get_name().unwrap(),
+        parameter_types
+            .iter()
+            .zip(parameter_names.iter())
+            .map(|(ty, name)| format!("{} {}", ty, name))
+            .collect::<Vec<_>>()
+            .join(", "),
+        qualifiers
+            .iter()
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" ")
+    ));
+    lines.push(format!(
+        "\t{}_calls.emplace_back({});",
+        recorder_name,
+        parameter_types
+            .iter()
+            .zip(parameter_names.iter())
+            .map(|(ty, name)| if is_passed_by_value(ty) {
+                format!("std::move({})", name)
+            } else {
+                name.clone()
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+    if let Some(result) = &result {
+        lines.push(format!("\treturn {};", result.expression));
+    }
+    lines.push("}".to_string());
+
+    lines.join("\n\t")
+}
+
+/// The member variable in which the canned result of a spied method is stored, and the expression
+/// through which it is returned.
+struct ResultStorage {
+    declaration: String,
+    expression: String,
+}
+
+/// References are stored as pointers, since the referenced type may be abstract. Move-only values
+/// are produced by a factory, so that each call returns a fresh one.
+fn get_result_storage(result_type: Type, member: &str) -> Option<ResultStorage> {
+    let display_name = result_type.get_display_name();
+    let (ty, init, expression) = match result_type.get_kind() {
+        TypeKind::Void => return None,
+        TypeKind::LValueReference => (
+            format!(
+                "{}*",
+                result_type.get_pointee_type().unwrap().get_display_name()
+            ),
+            "{}".to_string(),
+            format!("*{}", member),
+        ),
+        TypeKind::RValueReference => (
+            format!(
+                "{}*",
+                result_type.get_pointee_type().unwrap().get_display_name()
+            ),
+            "{}".to_string(),
+            format!("std::move(*{})", member),
+        ),
+        _ if is_move_only_type(result_type) => (
+            format!("std::function<{}()>", display_name),
+            format!("{{[] {{ return {}{{}}; }}}}", display_name),
+            format!("{}()", member),
+        ),
+        _ => (
+            get_storable_type(&display_name),
+            "{}".to_string(),
+            member.to_string(),
+        ),
+    };
+    Some(ResultStorage {
+        declaration: format!("mutable {} {}{};", ty, member, init),
+        expression,
+    })
+}
+
+fn is_passed_by_value(ty: &str) -> bool {
+    !ty.ends_with('&') && !ty.ends_with('*') && !ty.ends_with("const")
+}
+
+/// Turns a parameter or return type into a type which can be stored in a member variable of the
+/// spy: references and top-level `const` are stripped and non-owning string views are replaced by
+/// `std::string`.
+pub fn get_storable_type(ty: &str) -> String {
+    let ty = ty.trim().trim_end_matches('&').trim_end();
+    let ty = ty
+        .strip_suffix("const")
+        .filter(|t| t.ends_with(|c: char| c.is_whitespace() || c == '*'))
+        .map(str::trim_end)
+        .or_else(|| {
+            ty.strip_prefix("const ")
+                .filter(|t| !t.contains('*'))
+                .map(str::trim_start)
+        })
+        .unwrap_or(ty);
+
+    match ty {
+        "std::string_view" | "string_view" => "std::string".to_string(),
+        _ => ty.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod storable_type_tests {
+    use super::*;
+
+    #[test]
+    fn value_types_are_stored_as_is() {
+        assert_eq!(get_storable_type("int"), "int");
+        assert_eq!(
+            get_storable_type("std::map<int, double>"),
+            "std::map<int, double>"
+        );
+    }
+
+    #[test]
+    fn references_are_decayed() {
+        assert_eq!(get_storable_type("int &"), "int");
+        assert_eq!(get_storable_type("const int&"), "int");
+        assert_eq!(get_storable_type("int const&"), "int");
+        assert_eq!(get_storable_type("std::string &&"), "std::string");
+    }
+
+    #[test]
+    fn top_level_const_is_removed() {
+        assert_eq!(get_storable_type("const float"), "float");
+        assert_eq!(get_storable_type("float const"), "float");
+        assert_eq!(get_storable_type("double* const"), "double*");
+    }
+
+    #[test]
+    fn pointers_to_const_are_retained() {
+        assert_eq!(get_storable_type("const double *"), "const double *");
+        assert_eq!(get_storable_type("double const*"), "double const*");
+    }
+
+    #[test]
+    fn string_views_are_stored_as_strings() {
+        assert_eq!(get_storable_type("std::string_view"), "std::string");
+        assert_eq!(get_storable_type("const std::string_view &"), "std::string");
+    }
+}
+
+#[cfg(test)]
+mod generate_spy_class_from_interface {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn spy_records_arguments_and_returns_canned_value() {
+        test_class_from_source(
+            r#"
+                namespace std { struct string; }
+                struct Foo {
+                    virtual bool foo(int x, std::string const& s) const = 0;
+                    virtual void bar() noexcept = 0;
+                };
+            "#,
+            "Foo",
+            |class| {
+                assert_eq_upto_whitespace(
+                    &generate_spy_definition(class, "FooSpy"),
+                    r#"
+                        struct FooSpy : Foo {
+                            mutable std::vector<std::tuple<int, std::string>> foo_calls;
+                            mutable bool foo_result{};
+                            bool foo(int x, std::string const& s) const override {
+                                foo_calls.emplace_back(std::move(x), s);
+                                return foo_result;
+                            }
+
+                            mutable std::vector<std::tuple<>> bar_calls;
+                            void bar() noexcept override {
+                                bar_calls.emplace_back();
+                            }
+                        };
+                    "#,
+                )
+            },
+        )
+    }
+
+    #[test]
+    fn references_are_returned_through_pointers() {
+        test_class_from_source(
+            r#"
+                struct Bar {
+                    virtual void bar() = 0;
+                };
+                struct Foo {
+                    virtual const Bar& foo() const = 0;
+                    virtual Bar&& baz() = 0;
+                };
+            "#,
+            "Foo",
+            |class| {
+                assert_eq_upto_whitespace(
+                    &generate_spy_definition(class, "FooSpy"),
+                    r#"
+                        struct FooSpy : Foo {
+                            mutable std::vector<std::tuple<>> foo_calls;
+                            mutable const Bar* foo_result{};
+                            const Bar& foo() const override {
+                                foo_calls.emplace_back();
+                                return *foo_result;
+                            }
+
+                            mutable std::vector<std::tuple<>> baz_calls;
+                            mutable Bar* baz_result{};
+                            Bar&& baz() override {
+                                baz_calls.emplace_back();
+                                return std::move(*baz_result);
+                            }
+                        };
+                    "#,
+                )
+            },
+        )
+    }
+
+    #[test]
+    fn move_only_results_are_produced_by_factory() {
+        test_class_from_source(
+            r#"
+                namespace std {
+                    template <typename T> struct unique_ptr {
+                        unique_ptr(unique_ptr&&);
+                    };
+                }
+                struct Bar;
+                struct Foo {
+                    virtual std::unique_ptr<Bar> foo() = 0;
+                };
+            "#,
+            "Foo",
+            |class| {
+                assert_eq_upto_whitespace(
+                    &generate_spy_definition(class, "FooSpy"),
+                    r#"
+                        struct FooSpy : Foo {
+                            mutable std::vector<std::tuple<>> foo_calls;
+                            mutable std::function<std::unique_ptr<Bar>()> foo_result{[] { return std::unique_ptr<Bar>{}; }};
+                            std::unique_ptr<Bar> foo() override {
+                                foo_calls.emplace_back();
+                                return foo_result();
+                            }
+                        };
+                    "#,
+                )
+            },
+        )
+    }
+
+    #[test]
+    fn unnamed_parameters_do_not_shadow_named_ones() {
+        test_class_from_source(
+            r#"
+                struct Foo {
+                    virtual void foo(int arg1, int) = 0;
+                };
+            "#,
+            "Foo",
+            |class| {
+                assert_eq_upto_whitespace(
+                    &generate_spy_definition(class, "FooSpy"),
+                    r#"
+                        struct FooSpy : Foo {
+                            mutable std::vector<std::tuple<int, int>> foo_calls;
+                            void foo(int arg1, int arg1_) override {
+                                foo_calls.emplace_back(std::move(arg1), std::move(arg1_));
+                            }
+                        };
+                    "#,
+                )
+            },
+        )
+    }
+
+    #[test]
+    fn overloads_are_recorded_separately() {
+        test_class_from_source(
+            r#"
+                struct Foo {
+                    virtual void foo(int) = 0;
+                    virtual void foo(double*) = 0;
+                };
+            "#,
+            "Foo",
+            |class| {
+                assert_eq_upto_whitespace(
+                    &generate_spy_definition(class, "FooSpy"),
+                    r#"
+                        struct FooSpy : Foo {
+                            mutable std::vector<std::tuple<int>> foo_calls;
+                            void foo(int arg0) override {
+                                foo_calls.emplace_back(std::move(arg0));
+                            }
+
+                            mutable std::vector<std::tuple<double*>> foo2_calls;
+                            void foo(double* arg0) override {
+                                foo2_calls.emplace_back(arg0);
+                            }
+                        };
+                    "#,
+                )
+            },
+        )
+    }
+}
@@ -0,0 +1,101 @@
+use clang::*;
+use itertools::Itertools;
+
+use crate::mock_generation::*;
+
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Suggests the names of class definitions in the translation unit (outside of system headers)
+/// which are closest to the given, unknown class name. Abstract classes are ranked ahead of
+/// concrete ones at the same edit distance.
+pub fn suggest_class_names(tu: &TranslationUnit, class_name: &str) -> Vec<String> {
+    let unqualified_name = class_name.rsplit("::").next().unwrap_or(class_name);
+    let max_distance = (unqualified_name.chars().count() / 3).max(1);
+
+    let mut candidates = Vec::new();
+    tu.get_entity().visit_children(|entity, _| {
+        if entity.is_in_system_header() {
+            return EntityVisitResult::Continue;
+        }
+        if matches!(
+            entity.get_kind(),
+            EntityKind::ClassDecl | EntityKind::StructDecl
+        ) && entity.is_definition()
+        {
+            if let Some(name) = entity.get_name() {
+                let distance =
+                    edit_distance(&name.to_lowercase(), &unqualified_name.to_lowercase());
+                if distance <= max_distance {
+                    candidates.push((distance, !is_abstract_class(entity), entity));
+                }
+            }
+        }
+        EntityVisitResult::Recurse
+    });
+
+    candidates
+        .into_iter()
+        .sorted_by_key(|&(distance, concrete, _)| (distance, concrete))
+        .map(|(_, _, entity)| get_qualified_name(entity))
+        .unique()
+        .take(MAX_SUGGESTIONS)
+        .collect()
+}
+
+/// The Levenshtein distance between two strings, counted in characters.
+fn edit_distance(lhs: &str, rhs: &str) -> usize {
+    let rhs: Vec<_> = rhs.chars().collect();
+    let mut row: Vec<_> = (0..=rhs.len()).collect();
+    for (i, l) in lhs.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &r) in rhs.iter().enumerate() {
+            let substitution = diagonal + if l == r { 0 } else { 1 };
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[rhs.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn edit_distance_counts_insertions_deletions_and_substitutions() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("Storage", "Storage"), 0);
+        assert_eq!(edit_distance("Storage", "Storag"), 1);
+        assert_eq!(edit_distance("Storage", "Sttorage"), 1);
+        assert_eq!(edit_distance("Storage", "Storafe"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn abstract_classes_are_suggested_first() {
+        test_tu_from_source(
+            r#"
+                struct Storages {
+                    int size();
+                };
+                namespace Bar {
+                    struct Storage {
+                        virtual void save() = 0;
+                    };
+                }
+                struct Clock {
+                    virtual int now() = 0;
+                };
+            "#,
+            |tu| {
+                assert_eq!(
+                    suggest_class_names(tu, "Storag"),
+                    vec!["Bar::Storage", "Storages"]
+                );
+                assert!(suggest_class_names(tu, "Logger").is_empty());
+            },
+        );
+    }
+}
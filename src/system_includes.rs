@@ -0,0 +1,408 @@
+use crate::compile_commands::matches_pattern;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+const CACHE_FILE_NAME: &str = "system-includes.txt";
+
+/// Prefixes of arguments which affect a compiler's implicit include directories and are therefore
+/// passed on when querying them.
+const RELEVANT_ARGUMENT_PREFIXES: &[&str] = &[
+    "--target=",
+    "--sysroot=",
+    "-stdlib=",
+    "--gcc-toolchain=",
+    "-m32",
+    "-m64",
+];
+
+/// The implicit include directories and the resource directory of a compiler driver.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SystemIncludes {
+    pub resource_dir: Option<String>,
+    pub include_dirs: Vec<String>,
+}
+
+impl SystemIncludes {
+    /// The arguments which make libclang search the same system include directories as the
+    /// compiler driver.
+    pub fn get_arguments(&self) -> Vec<String> {
+        self.resource_dir
+            .iter()
+            .map(|dir| format!("-resource-dir={}", dir))
+            .chain(
+                self.include_dirs
+                    .iter()
+                    .flat_map(|dir| vec!["-isystem".to_string(), dir.clone()]),
+            )
+            .collect()
+    }
+}
+
+/// Detects the system include directories of the compiler driver for the given language (`c` or
+/// `c++`) by running it with `-E -v`. If the compiler does not exist on this machine, a stand-in
+/// with the same name is looked up on the `PATH`. If any patterns of allowed compilers are given,
+/// the compiler is only run if its path matches one of them, since compile commands databases may
+/// name arbitrary executables. Results are cached on disk, keyed by the compiler's path and
+/// modification time, the language, and the arguments which affect the include directories.
+pub fn detect_system_includes(
+    compiler: &str,
+    language: &str,
+    arguments: &[String],
+    allowed_compilers: &[String],
+) -> Option<SystemIncludes> {
+    detect_system_includes_with_cache(
+        compiler,
+        language,
+        arguments,
+        allowed_compilers,
+        get_cache_file(),
+    )
+}
+
+fn detect_system_includes_with_cache(
+    compiler: &str,
+    language: &str,
+    arguments: &[String],
+    allowed_compilers: &[String],
+    cache_file: Option<PathBuf>,
+) -> Option<SystemIncludes> {
+    let compiler = find_compiler(compiler)?;
+    let path = compiler.to_str()?;
+    if !allowed_compilers.is_empty()
+        && !allowed_compilers
+            .iter()
+            .any(|pattern| matches_pattern(path, pattern))
+    {
+        return None;
+    }
+    let modified = std::fs::metadata(&compiler)
+        .and_then(|metadata| metadata.modified())
+        .ok()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    let relevant_arguments: Vec<_> = arguments
+        .iter()
+        .filter(|arg| {
+            RELEVANT_ARGUMENT_PREFIXES
+                .iter()
+                .any(|prefix| arg.starts_with(prefix))
+        })
+        .cloned()
+        .collect();
+    let key = CacheKey {
+        compiler: path.to_string(),
+        modified,
+        language: language.to_string(),
+        arguments: relevant_arguments.join(" "),
+    };
+
+    let mut entries: Vec<_> = cache_file
+        .as_ref()
+        .and_then(|file| std::fs::read_to_string(file).ok())
+        .map(|contents| contents.lines().filter_map(parse_cache_entry).collect())
+        .unwrap_or_default();
+    if let Some((_, includes)) = entries.iter().find(|(entry_key, _)| *entry_key == key) {
+        return Some(includes.clone());
+    }
+
+    let includes = query_system_includes(&compiler, language, &relevant_arguments)?;
+    if let Some(file) = cache_file {
+        // Entries for an earlier version of the compiler are replaced rather than accumulated.
+        entries.retain(|(entry_key, _)| !entry_key.is_superseded_by(&key));
+        entries.push((key, includes.clone()));
+        let _ = write_cache_entries(&file, &entries);
+    }
+    Some(includes)
+}
+
+fn find_compiler(compiler: &str) -> Option<PathBuf> {
+    let path = Path::new(compiler);
+    if path.is_file() {
+        return Some(path.to_path_buf());
+    }
+    let name = path.file_name()?;
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(name))
+        .find(|candidate| candidate.is_file())
+}
+
+fn query_system_includes(
+    compiler: &Path,
+    language: &str,
+    arguments: &[String],
+) -> Option<SystemIncludes> {
+    let output = Command::new(compiler)
+        .args(arguments)
+        .args(["-E", "-x", language, "-", "-v"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .output()
+        .ok()?;
+    let include_dirs = parse_verbose_output(&String::from_utf8_lossy(&output.stderr));
+    if include_dirs.is_empty() {
+        return None;
+    }
+
+    // Only clang knows about resource directories; other compilers fail or echo the option.
+    let resource_dir = Command::new(compiler)
+        .args(arguments)
+        .arg("-print-resource-dir")
+        .stdin(Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|dir| Path::new(dir).is_dir());
+
+    Some(SystemIncludes {
+        resource_dir,
+        include_dirs,
+    })
+}
+
+/// Extracts the include directories from the output of a compiler run with `-E -v`, which lists
+/// them between `#include <...> search starts here:` and `End of search list.`
+fn parse_verbose_output(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .skip_while(|line| !line.starts_with("#include <...> search starts here:"))
+        .skip(1)
+        .take_while(|line| !line.starts_with("End of search list."))
+        .map(|line| {
+            line.trim()
+                .trim_end_matches(" (framework directory)")
+                .to_string()
+        })
+        .collect()
+}
+
+fn get_cache_file() -> Option<PathBuf> {
+    std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .map(|dir| dir.join("mockery").join(CACHE_FILE_NAME))
+}
+
+fn write_cache_entries(file: &Path, entries: &[(CacheKey, SystemIncludes)]) -> std::io::Result<()> {
+    std::fs::create_dir_all(file.parent().unwrap())?;
+    // Written to a temporary file first, so that concurrent runs never read a partial cache.
+    let temp_file = file.with_extension(format!("{}.tmp", std::process::id()));
+    std::fs::write(
+        &temp_file,
+        entries
+            .iter()
+            .map(|(key, includes)| format!("{}\n", format_cache_entry(key, includes)))
+            .collect::<String>(),
+    )?;
+    std::fs::rename(&temp_file, file)
+}
+
+/// What the detected include directories depend on.
+#[derive(Clone, Debug, PartialEq)]
+struct CacheKey {
+    compiler: String,
+    modified: u64,
+    language: String,
+    arguments: String,
+}
+
+impl CacheKey {
+    /// Whether this is the key of the same query for a different version of the compiler.
+    fn is_superseded_by(&self, other: &CacheKey) -> bool {
+        self.compiler == other.compiler
+            && self.language == other.language
+            && self.arguments == other.arguments
+    }
+}
+
+/// A cache entry is a tab-separated line of the compiler's path, its modification time, the
+/// language, the relevant arguments, the resource directory (empty if unknown), and the include
+/// directories.
+fn format_cache_entry(key: &CacheKey, includes: &SystemIncludes) -> String {
+    let modified = key.modified.to_string();
+    [
+        key.compiler.as_str(),
+        &modified,
+        &key.language,
+        &key.arguments,
+        includes.resource_dir.as_deref().unwrap_or_default(),
+    ]
+    .iter()
+    .copied()
+    .chain(includes.include_dirs.iter().map(String::as_str))
+    .collect::<Vec<_>>()
+    .join("\t")
+}
+
+fn parse_cache_entry(line: &str) -> Option<(CacheKey, SystemIncludes)> {
+    let mut fields = line.split('\t');
+    let key = CacheKey {
+        compiler: fields.next()?.to_string(),
+        modified: fields.next()?.parse().ok()?,
+        language: fields.next()?.to_string(),
+        arguments: fields.next()?.to_string(),
+    };
+    let resource_dir = Some(fields.next()?)
+        .filter(|dir| !dir.is_empty())
+        .map(str::to_string);
+    Some((
+        key,
+        SystemIncludes {
+            resource_dir,
+            include_dirs: fields.map(str::to_string).collect(),
+        },
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn include_directories_are_parsed_from_verbose_output() {
+        let output = r#"
+Using built-in specs.
+Target: x86_64-linux-gnu
+#include "..." search starts here:
+#include <...> search starts here:
+ /usr/include/c++/11
+ /usr/lib/gcc/x86_64-linux-gnu/11/include
+ /usr/include
+ /System/Library/Frameworks (framework directory)
+End of search list.
+"#;
+        assert_eq!(
+            parse_verbose_output(output),
+            vec![
+                "/usr/include/c++/11",
+                "/usr/lib/gcc/x86_64-linux-gnu/11/include",
+                "/usr/include",
+                "/System/Library/Frameworks",
+            ]
+        );
+    }
+
+    #[test]
+    fn system_includes_are_passed_as_arguments() {
+        let includes = SystemIncludes {
+            resource_dir: Some("/usr/lib/clang/14".to_string()),
+            include_dirs: vec!["/usr/include".to_string()],
+        };
+        assert_eq!(
+            includes.get_arguments(),
+            vec![
+                "-resource-dir=/usr/lib/clang/14",
+                "-isystem",
+                "/usr/include"
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    fn create_stub_compiler(name: &str) -> (PathBuf, PathBuf) {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = std::env::temp_dir().join(format!("mockery-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let marker = dir.join("was-run");
+        let _ = std::fs::remove_file(&marker);
+        let compiler = dir.join(name);
+        std::fs::write(
+            &compiler,
+            format!(
+                "#!/bin/sh\ntouch '{}'\nfor arg in \"$@\"; do [ \"$arg\" = -print-resource-dir ] && exit 1; done\nprintf '#include <...> search starts here:\\n /opt/stub/include\\nEnd of search list.\\n' >&2\n",
+                marker.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&compiler, std::fs::Permissions::from_mode(0o755)).unwrap();
+        (compiler, marker)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn compilers_are_not_run_unless_allowed() {
+        let (compiler, marker) = create_stub_compiler("untrusted-c++");
+        let compiler = compiler.to_str().unwrap();
+        assert_eq!(
+            detect_system_includes_with_cache(
+                compiler,
+                "c++",
+                &[],
+                &["/usr/bin/*".to_string()],
+                None
+            ),
+            None
+        );
+        assert!(!marker.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn compilers_are_queried_by_default_and_cached() {
+        let (compiler, marker) = create_stub_compiler("default-c++");
+        let cache_file = compiler.with_file_name("cache.txt");
+        let _ = std::fs::remove_file(&cache_file);
+        let detect = |arguments: &[String]| {
+            detect_system_includes_with_cache(
+                compiler.to_str().unwrap(),
+                "c++",
+                arguments,
+                &[],
+                Some(cache_file.clone()),
+            )
+        };
+        let expected = Some(SystemIncludes {
+            resource_dir: None,
+            include_dirs: vec!["/opt/stub/include".to_string()],
+        });
+        assert_eq!(detect(&[]), expected);
+        assert!(marker.exists());
+
+        std::fs::remove_file(&marker).unwrap();
+        assert_eq!(detect(&[]), expected);
+        assert_eq!(detect(&["-m32".to_string()]), expected);
+        assert!(marker.exists());
+        assert_eq!(
+            std::fs::read_to_string(&cache_file)
+                .unwrap()
+                .lines()
+                .count(),
+            2
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn allowed_compilers_are_queried() {
+        let (compiler, marker) = create_stub_compiler("trusted-c++");
+        let path = compiler.to_str().unwrap();
+        assert_eq!(
+            detect_system_includes_with_cache(path, "c++", &[], &["*/trusted-*".to_string()], None),
+            Some(SystemIncludes {
+                resource_dir: None,
+                include_dirs: vec!["/opt/stub/include".to_string()],
+            })
+        );
+        assert!(marker.exists());
+    }
+
+    #[test]
+    fn cache_entries_round_trip() {
+        let includes = SystemIncludes {
+            resource_dir: None,
+            include_dirs: vec![
+                "/usr/include/c++/11".to_string(),
+                "/usr/include".to_string(),
+            ],
+        };
+        let key = CacheKey {
+            compiler: "/usr/bin/g++".to_string(),
+            modified: 1234,
+            language: "c++".to_string(),
+            arguments: "-m32".to_string(),
+        };
+        let entry = format_cache_entry(&key, &includes);
+        assert_eq!(parse_cache_entry(&entry), Some((key, includes)));
+    }
+}
@@ -33,6 +33,29 @@ pub fn test_class_from_source<C: FnOnce(Entity)>(code: &str, class_name: &str, c
     })
 }
 
+/// Runs the callback on the first member declared in a test class, given as `func_decl`.
+pub fn test_method_for_function<C: Fn(Entity)>(func_decl: &str, callback: C) {
+    test_tu_from_source(
+        &format!(
+            r#"
+                template <typename T, typename U> struct pair;
+                namespace std {{ struct string; }}
+                struct TestClass {{ {} }};
+            "#,
+            func_decl
+        ),
+        |tu| {
+            callback(
+                *find_class_entity(tu, "TestClass")
+                    .unwrap()
+                    .get_children()
+                    .first()
+                    .unwrap(),
+            )
+        },
+    )
+}
+
 fn split_around<P: FnMut(char) -> bool>(input: &str, pat: P) -> Vec<&str> {
     use std::iter::once;
     once(Wrapping(usize::MAX))